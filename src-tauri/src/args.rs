@@ -0,0 +1,289 @@
+use regex::Regex;
+
+use crate::cookies::browser_cookie_args;
+use crate::models::{AppSettings, AuthCredentials, OutputTemplatePreset};
+
+/// Flags the app sets itself; letting users override them would break progress
+/// parsing, temp-dir cleanup or the bundled ffmpeg/aria2c wiring.
+const RESERVED_ARGS: &[&str] = &[
+    "-o",
+    "--output",
+    "-P",
+    "--paths",
+    "--downloader",
+    "--external-downloader",
+    "--downloader-args",
+    "--external-downloader-args",
+    "--ffmpeg-location",
+    "--newline",
+    "--progress",
+    "--no-progress",
+    "-q",
+    "--quiet",
+    "-J",
+    "--dump-single-json",
+    "-j",
+    "--dump-json",
+    "--flat-playlist",
+    // Credentials must go through `auth_args` so they stay out of extra-arg settings.
+    "--username",
+    "-u",
+    "--password",
+    "-p",
+    "--twofactor",
+    "-2",
+];
+
+/// `after_long_flag` marks a token that may be the value of the preceding
+/// `--option`; there only an exact short flag counts, so values such as
+/// `--sub-langs -pt` aren't mistaken for `-p t`.
+fn reserved_flag(arg: &str, after_long_flag: bool) -> Option<&'static str> {
+    let flag = arg.split_once('=').map(|(name, _)| name).unwrap_or(arg);
+
+    RESERVED_ARGS.iter().copied().find(|reserved| {
+        if reserved.starts_with("--") {
+            flag == *reserved
+        } else if after_long_flag {
+            arg == *reserved
+        } else {
+            // Short options may carry their value inline, e.g. `-o%(id)s.%(ext)s`.
+            arg.starts_with(reserved)
+        }
+    })
+}
+
+/// Flags whose value may carry credentials, cookies or tokens.
+const SENSITIVE_ARGS: &[&str] = &[
+    "-u",
+    "--username",
+    "-p",
+    "--password",
+    "-2",
+    "--twofactor",
+    "--video-password",
+    "--ap-username",
+    "--ap-password",
+    "--cookies",
+    "--proxy",
+    "--geo-verification-proxy",
+    "--add-header",
+];
+
+/// Copy of `args` that is safe to print: values following sensitive flags
+/// (or attached with `=`) are replaced with `***`.
+pub fn redact_args(args: &[String]) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut redact_next = false;
+
+    for arg in args {
+        if redact_next {
+            redacted.push("***".to_string());
+            redact_next = false;
+            continue;
+        }
+
+        match arg.split_once('=') {
+            Some((flag, _)) if SENSITIVE_ARGS.contains(&flag) => {
+                redacted.push(format!("{}=***", flag));
+            }
+            _ => {
+                redact_next = SENSITIVE_ARGS.contains(&arg.as_str());
+                redacted.push(arg.clone());
+            }
+        }
+    }
+
+    redacted
+}
+
+pub fn validate_extra_args(args: &[String]) -> Result<(), String> {
+    let mut after_long_flag = false;
+    for arg in args {
+        let arg = arg.trim();
+        if !arg.starts_with('-') {
+            after_long_flag = false;
+            continue;
+        }
+        let reserved = reserved_flag(arg, after_long_flag);
+        after_long_flag = arg.starts_with("--") && !arg.contains('=');
+        if let Some(flag) = reserved {
+            return Err(format!(
+                "Extra argument '{}' conflicts with '{}', which is managed by the app",
+                arg, flag
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks user-supplied ffmpeg options and returns the `--postprocessor-args`
+/// value. A `ffmpeg:`, `ffmpeg_i:`/`ffmpeg_o:` or `PP+ffmpeg_o:` style
+/// prefix is kept; any other executable is rejected so these args can't
+/// target other PPs. yt-dlp splits the options itself, no shell is involved.
+pub fn ffmpeg_postprocessor_args(value: &str) -> Result<Option<String>, String> {
+    let value = value.trim();
+    let namespace_re = Regex::new(r"^([A-Za-z]\w*(?:\+\w+)?):").unwrap();
+    let ffmpeg_re = Regex::new(r"(?i)^(?:[a-z]\w*\+)?ffmpeg(?:_[io]\d*)?$").unwrap();
+    let (namespace, args) = match namespace_re.captures(value) {
+        Some(caps) if ffmpeg_re.is_match(&caps[1]) => {
+            (caps[1].to_string(), value[caps[0].len()..].trim())
+        }
+        Some(caps) => {
+            return Err(format!(
+                "Postprocessor args may only target ffmpeg, not '{}'",
+                &caps[1]
+            ));
+        }
+        None => ("ffmpeg".to_string(), value),
+    };
+    if args.is_empty() {
+        return Ok(None);
+    }
+    if shlex::split(args).is_none() {
+        return Err(format!("Postprocessor args {:?} have an unclosed quote", args));
+    }
+    Ok(Some(format!("{}:{}", namespace, args)))
+}
+
+/// Normalizes an `--http-chunk-size` value such as "10M" or "512k"; empty
+/// means no chunking.
+pub fn http_chunk_size(value: &str) -> Result<Option<String>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let size_re = Regex::new(r"^(\d+(?:\.\d+)?)([kKmMgG]?)$").unwrap();
+    let caps = size_re.captures(value).ok_or_else(|| {
+        format!("Invalid HTTP chunk size {:?}; use a size like \"10M\" or \"512K\"", value)
+    })?;
+    if caps[1].parse::<f64>().map_or(true, |size| size <= 0.0) {
+        return Err("HTTP chunk size must be greater than zero".to_string());
+    }
+    Ok(Some(format!("{}{}", &caps[1], caps[2].to_ascii_uppercase())))
+}
+
+pub fn auth_args(auth: &AuthCredentials) -> Vec<String> {
+    let mut args = Vec::new();
+    let credentials = [
+        ("--username", &auth.username),
+        ("--password", &auth.password),
+        ("--twofactor", &auth.twofactor),
+    ];
+
+    for (flag, value) in credentials {
+        if let Some(value) = value.as_deref().filter(|value| !value.is_empty()) {
+            args.push(flag.to_string());
+            args.push(value.to_string());
+        }
+    }
+
+    if let Some(browser) = auth
+        .cookies_from_browser
+        .as_deref()
+        .map(str::trim)
+        .filter(|browser| !browser.is_empty())
+    {
+        args.extend(browser_cookie_args(browser));
+    }
+
+    args
+}
+
+/// Joins args into one POSIX shell command line, single-quoting the ones
+/// that need it; an embedded `'` becomes `'\''`.
+pub fn shell_join(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            let plain = !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.,:/=+@%".contains(c));
+            if plain {
+                arg.clone()
+            } else {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Rejects output templates whose literal parts could escape the download dir.
+/// Field values are sanitized by yt-dlp, so only the text around `%(...)s`
+/// needs checking.
+pub fn validate_output_template(template: &str) -> Result<(), String> {
+    let template = template.trim();
+    if template.is_empty() {
+        return Err("Output template cannot be empty".to_string());
+    }
+    if !template.contains("%(ext)s") {
+        return Err(
+            "Output template must include %(ext)s so files keep their extension".to_string(),
+        );
+    }
+
+    let field_re = Regex::new(r"%\([^)]*\)[-#0 +]*\d*(?:\.\d+)?[a-zA-Z]").unwrap();
+    let literal = field_re.replace_all(template, "x");
+    let escapes = literal.starts_with(['/', '\\'])
+        || literal.contains(':')
+        || literal
+            .split(['/', '\\'])
+            .any(|segment| segment == ".." || segment == ".");
+    if escapes {
+        return Err(format!(
+            "Output template '{}' must be a relative path inside the download directory",
+            template
+        ));
+    }
+
+    Ok(())
+}
+
+/// aria2c allows at most 16 connections per server.
+const ARIA2C_MAX_CONNECTIONS: u8 = 16;
+
+pub fn validate_aria2c_settings(settings: &AppSettings) -> Result<(), String> {
+    for (name, value) in [
+        ("aria2c connections", settings.aria2c_connections),
+        ("aria2c split", settings.aria2c_split),
+    ] {
+        if let Some(value) = value {
+            if !(1..=ARIA2C_MAX_CONNECTIONS).contains(&value) {
+                return Err(format!(
+                    "{} must be between 1 and {}, got {}",
+                    name, ARIA2C_MAX_CONNECTIONS, value
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `--downloader-args` value for aria2c built from the connection settings.
+pub fn aria2c_downloader_args(settings: &AppSettings) -> String {
+    let connections = settings
+        .aria2c_connections
+        .unwrap_or(ARIA2C_MAX_CONNECTIONS)
+        .clamp(1, ARIA2C_MAX_CONNECTIONS);
+    let split = settings
+        .aria2c_split
+        .unwrap_or(ARIA2C_MAX_CONNECTIONS)
+        .clamp(1, ARIA2C_MAX_CONNECTIONS);
+    format!(
+        "aria2c:-x{} -s{} -k1M --file-allocation=none --check-certificate=false",
+        connections, split
+    )
+}
+
+pub fn resolve_output_template(settings: &AppSettings) -> Result<String, String> {
+    let template = match settings.output_template_preset {
+        OutputTemplatePreset::Custom => settings
+            .custom_output_template
+            .clone()
+            .ok_or("Custom output template preset selected but no template is set")?,
+        preset => preset.template().unwrap_or_default().to_string(),
+    };
+    validate_output_template(&template)?;
+    Ok(template.trim().to_string())
+}
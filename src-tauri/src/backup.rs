@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use tauri::{AppHandle, Emitter};
+
+use crate::models::{ExportedState, ImportedState};
+use crate::queue::get_queue_state;
+use crate::state::DOWNLOAD_QUEUE;
+
+/// Bumped whenever `ExportedState` changes incompatibly.
+const STATE_EXPORT_VERSION: u32 = 1;
+
+/// Writes `history` (as handed over by the frontend) and the pending backend
+/// queue to `path`. Credentials in download options are never exported.
+#[tauri::command]
+pub fn export_state(path: String, history: Option<Vec<serde_json::Value>>) -> Result<(), String> {
+    let queue = DOWNLOAD_QUEUE
+        .lock()
+        .map_err(|e| e.to_string())?
+        .pending
+        .iter()
+        .cloned()
+        .collect();
+    let state = ExportedState {
+        version: STATE_EXPORT_VERSION,
+        exported_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0),
+        history: history.unwrap_or_default(),
+        queue,
+    };
+
+    let contents = serde_json::to_string_pretty(&state).map_err(|e| e.to_string())?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
+
+/// Reads a file written by `export_state`, adds its queue to the backend queue
+/// and pauses the queue so nothing starts until the user resumes it. The
+/// history is returned for the frontend to merge.
+#[tauri::command]
+pub fn import_state(app: AppHandle, path: String) -> Result<ImportedState, String> {
+    if !Path::new(&path).is_file() {
+        return Err(format!("{:?} does not exist", path));
+    }
+    let contents =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| format!("{:?} is not a valid export file: {}", path, e))?;
+
+    match value["version"].as_u64() {
+        Some(version) if version == u64::from(STATE_EXPORT_VERSION) => {}
+        Some(version) => {
+            return Err(format!(
+                "Export format version {} is not supported (expected {})",
+                version, STATE_EXPORT_VERSION
+            ));
+        }
+        None => return Err(format!("{:?} is not a dlpgui export file", path)),
+    }
+    let state: ExportedState = serde_json::from_value(value)
+        .map_err(|e| format!("{:?} is not a valid export file: {}", path, e))?;
+
+    let (queued, skipped) = {
+        let mut queue = DOWNLOAD_QUEUE.lock().map_err(|e| e.to_string())?;
+        let mut queued = 0;
+        let mut skipped = 0;
+        for job in state.queue {
+            let exists = queue.running.contains_key(&job.id)
+                || queue.pending.iter().any(|pending| pending.id == job.id);
+            if exists {
+                skipped += 1;
+            } else {
+                queue.pending.push_back(job);
+                queued += 1;
+            }
+        }
+        if queued > 0 {
+            queue.paused = true;
+        }
+        (queued, skipped)
+    };
+
+    if queued > 0 {
+        let _ = app.emit("queue-state", get_queue_state()?);
+    }
+
+    Ok(ImportedState {
+        history: state.history,
+        queued,
+        skipped,
+    })
+}
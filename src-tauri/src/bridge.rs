@@ -43,7 +43,7 @@ async fn write_bridge_response(
 ) -> Result<(), String> {
     let response = format!(
         "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\nAccess-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: content-type\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\n\r\n{body}",
-        body.as_bytes().len()
+        body.len()
     );
 
     stream
@@ -192,7 +192,7 @@ pub fn start_extension_bridge(app: AppHandle) {
             Ok(listener) => {
                 EXTENSION_BRIDGE_READY.store(true, Ordering::Relaxed);
                 set_extension_bridge_error(None);
-                println!("[bridge] Chrome extension bridge listening on {}", bind_addr);
+                tracing::info!("Chrome extension bridge listening on {}", bind_addr);
 
                 loop {
                     match listener.accept().await {
@@ -202,12 +202,12 @@ pub fn start_extension_bridge(app: AppHandle) {
                                 if let Err(err) =
                                     handle_extension_bridge_connection(stream, app_handle).await
                                 {
-                                    println!("[bridge] Request failed: {}", err);
+                                    tracing::warn!("Extension bridge request failed: {}", err);
                                 }
                             });
                         }
                         Err(err) => {
-                            println!("[bridge] Accept failed: {}", err);
+                            tracing::warn!("Extension bridge accept failed: {}", err);
                         }
                     }
                 }
@@ -217,7 +217,7 @@ pub fn start_extension_bridge(app: AppHandle) {
                     format!("Failed to bind extension bridge on {}: {}", bind_addr, err);
                 EXTENSION_BRIDGE_READY.store(false, Ordering::Relaxed);
                 set_extension_bridge_error(Some(message.clone()));
-                println!("[bridge] {}", message);
+                tracing::warn!("{}", message);
             }
         }
     });
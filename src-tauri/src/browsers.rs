@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+
+use crate::models::BrowserProfile;
+
+/// Chromium-based browsers keep one folder per profile ("Default", "Profile 2")
+/// under a shared user-data directory.
+fn chromium_user_data_dir(browser: &str) -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    {
+        let local = PathBuf::from(std::env::var_os("LOCALAPPDATA")?);
+        let roaming = PathBuf::from(std::env::var_os("APPDATA")?);
+        match browser {
+            "chrome" => Some(local.join("Google").join("Chrome").join("User Data")),
+            "chromium" => Some(local.join("Chromium").join("User Data")),
+            "edge" => Some(local.join("Microsoft").join("Edge").join("User Data")),
+            "brave" => Some(
+                local
+                    .join("BraveSoftware")
+                    .join("Brave-Browser")
+                    .join("User Data"),
+            ),
+            "vivaldi" => Some(local.join("Vivaldi").join("User Data")),
+            "opera" => Some(roaming.join("Opera Software").join("Opera Stable")),
+            _ => None,
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let support = PathBuf::from(std::env::var_os("HOME")?)
+            .join("Library")
+            .join("Application Support");
+        match browser {
+            "chrome" => Some(support.join("Google").join("Chrome")),
+            "chromium" => Some(support.join("Chromium")),
+            "edge" => Some(support.join("Microsoft Edge")),
+            "brave" => Some(support.join("BraveSoftware").join("Brave-Browser")),
+            "vivaldi" => Some(support.join("Vivaldi")),
+            "opera" => Some(support.join("com.operasoftware.Opera")),
+            _ => None,
+        }
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let config = PathBuf::from(std::env::var_os("HOME")?).join(".config");
+        match browser {
+            "chrome" => Some(config.join("google-chrome")),
+            "chromium" => Some(config.join("chromium")),
+            "edge" => Some(config.join("microsoft-edge")),
+            "brave" => Some(config.join("BraveSoftware").join("Brave-Browser")),
+            "vivaldi" => Some(config.join("vivaldi")),
+            "opera" => Some(config.join("opera")),
+            _ => None,
+        }
+    }
+}
+
+fn firefox_profiles_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    {
+        Some(
+            PathBuf::from(std::env::var_os("APPDATA")?)
+                .join("Mozilla")
+                .join("Firefox")
+                .join("Profiles"),
+        )
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Some(
+            PathBuf::from(std::env::var_os("HOME")?)
+                .join("Library")
+                .join("Application Support")
+                .join("Firefox")
+                .join("Profiles"),
+        )
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        Some(
+            PathBuf::from(std::env::var_os("HOME")?)
+                .join(".mozilla")
+                .join("firefox"),
+        )
+    }
+}
+
+/// Reads the user-facing profile name Chromium stores in `Preferences`.
+fn chromium_profile_name(profile_dir: &Path) -> Option<String> {
+    let preferences = std::fs::read_to_string(profile_dir.join("Preferences")).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&preferences).ok()?;
+    json["profile"]["name"]
+        .as_str()
+        .map(|name| name.to_string())
+}
+
+fn list_chromium_profiles(user_data_dir: &Path) -> Vec<BrowserProfile> {
+    // Opera has no profile subfolders; the user-data dir is the profile.
+    if user_data_dir.join("Cookies").exists() || user_data_dir.join("Network").exists() {
+        return vec![BrowserProfile {
+            name: String::new(),
+            display_name: "Default".to_string(),
+            path: user_data_dir.to_string_lossy().to_string(),
+        }];
+    }
+
+    let Ok(entries) = std::fs::read_dir(user_data_dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join("Preferences").is_file())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().to_string();
+            Some(BrowserProfile {
+                display_name: chromium_profile_name(&path).unwrap_or_else(|| name.clone()),
+                name,
+                path: path.to_string_lossy().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn list_firefox_profiles(profiles_dir: &Path) -> Vec<BrowserProfile> {
+    let Ok(entries) = std::fs::read_dir(profiles_dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join("cookies.sqlite").is_file())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().to_string();
+            // Folder names look like "abcd1234.default-release".
+            let display_name = name
+                .split_once('.')
+                .map(|(_, label)| label.to_string())
+                .unwrap_or_else(|| name.clone());
+            Some(BrowserProfile {
+                name,
+                display_name,
+                path: path.to_string_lossy().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Lists the profiles of `browser` that can be passed to yt-dlp as
+/// `--cookies-from-browser BROWSER:PROFILE`.
+#[tauri::command]
+pub fn list_browser_profiles(browser: String) -> Result<Vec<BrowserProfile>, String> {
+    let browser = browser.trim().to_ascii_lowercase();
+    let mut profiles = if browser == "firefox" {
+        let dir = firefox_profiles_dir().ok_or("Could not locate the Firefox profile directory")?;
+        list_firefox_profiles(&dir)
+    } else {
+        let dir = chromium_user_data_dir(&browser)
+            .ok_or_else(|| format!("Profile listing is not supported for '{}'", browser))?;
+        list_chromium_profiles(&dir)
+    };
+
+    profiles.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+    Ok(profiles)
+}
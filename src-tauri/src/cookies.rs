@@ -0,0 +1,120 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tauri::{AppHandle, Manager};
+
+use crate::state::{COOKIE_CACHE, COOKIE_CACHE_DIR};
+
+/// Keeps the cookie files of concurrent yt-dlp runs apart.
+static COOKIE_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn cookie_cache_root(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path().app_local_data_dir().map_err(|e| e.to_string())?;
+    Ok(data_dir.join("cookies"))
+}
+
+/// Creates this session's cookie folder in the app data dir, which only the
+/// current user can read; the folder itself is private on Unix, too. Cookie
+/// folders left by a session that didn't shut down cleanly are removed.
+pub fn init_cookie_cache(app: &AppHandle) {
+    let session_dir = cookie_cache_root(app).and_then(|root| {
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).map_err(|e| e.to_string())?;
+        tempfile::Builder::new()
+            .prefix("session-")
+            .tempdir_in(&root)
+            .map_err(|e| e.to_string())
+    });
+    match session_dir {
+        Ok(session_dir) => {
+            if let Ok(mut cache_dir) = COOKIE_CACHE_DIR.lock() {
+                *cache_dir = Some(session_dir);
+            }
+        }
+        Err(err) => tracing::warn!("Failed to create cookie cache folder: {}", err),
+    }
+}
+
+/// yt-dlp args for `--cookies-from-browser SPEC`. The first use of a browser
+/// spec also dumps the cookies into a session cache file via `--cookies`;
+/// later calls read a copy of that file instead of opening the browser profile
+/// again, which would re-decrypt the store and can lock the browser's cookie
+/// DB. yt-dlp writes the jar back on exit, so every run gets its own file.
+pub fn browser_cookie_args(browser: &str) -> Vec<String> {
+    let from_browser = || vec!["--cookies-from-browser".to_string(), browser.to_string()];
+    let Ok(mut cache) = COOKIE_CACHE.lock() else {
+        return from_browser();
+    };
+    let Some(cache_dir) = COOKIE_CACHE_DIR
+        .lock()
+        .ok()
+        .and_then(|cache_dir| cache_dir.as_ref().map(|dir| dir.path().to_path_buf()))
+    else {
+        return from_browser();
+    };
+
+    let mut hasher = DefaultHasher::new();
+    browser.hash(&mut hasher);
+    // Not created here: yt-dlp writes it once it has the cookies.
+    let path = cache_dir.join(format!(
+        "{:016x}-{}.txt",
+        hasher.finish(),
+        COOKIE_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let populated = cache.get(browser).filter(|cached| {
+        std::fs::metadata(cached)
+            .map(|metadata| metadata.len() > 0)
+            .unwrap_or(false)
+    });
+    match populated.map(|cached| std::fs::copy(cached, &path)) {
+        Some(Ok(_)) => {
+            return vec!["--cookies".to_string(), path.to_string_lossy().to_string()];
+        }
+        Some(Err(err)) => tracing::warn!("Failed to copy cached cookies: {}", err),
+        None => {
+            cache.insert(browser.to_string(), path.clone());
+        }
+    }
+
+    let mut args = from_browser();
+    args.push("--cookies".to_string());
+    args.push(path.to_string_lossy().to_string());
+    args
+}
+
+/// Deletes every cached cookie file. Returns how many browser specs were
+/// cached.
+#[tauri::command]
+pub fn clear_cookie_cache() -> usize {
+    let count = COOKIE_CACHE
+        .lock()
+        .map(|mut cache| {
+            let count = cache.len();
+            cache.clear();
+            count
+        })
+        .unwrap_or(0);
+
+    if let Ok(cache_dir) = COOKIE_CACHE_DIR.lock() {
+        let entries = cache_dir
+            .as_ref()
+            .and_then(|dir| std::fs::read_dir(dir.path()).ok());
+        for entry in entries.into_iter().flatten().flatten() {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+    count
+}
+
+/// Removes this session's cookie folder on exit.
+pub fn remove_cookie_cache() {
+    if let Ok(mut cache) = COOKIE_CACHE.lock() {
+        cache.clear();
+    }
+    if let Ok(mut cache_dir) = COOKIE_CACHE_DIR.lock() {
+        cache_dir.take();
+    }
+}
@@ -0,0 +1,315 @@
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use tauri::AppHandle;
+use tauri_plugin_shell::{process::CommandEvent, ShellExt};
+
+use crate::args::aria2c_downloader_args;
+use crate::downloads::{
+    format_size, parse_size_to_bytes, parse_speed_to_bytes, resolve_ffmpeg_path,
+};
+use crate::errors::classify_ytdlp_error;
+use crate::models::{DownloadBenchmark, DownloadMethodBenchmark, ToolVersions, YoutubeDiagnostics};
+use crate::process::kill_process_tree;
+use crate::runtime::{base_ytdlp_args, detect_js_runtime, ytdlp_command};
+use crate::settings::current_settings;
+
+/// Measured from the first progress line, so extraction time isn't counted.
+const BENCHMARK_DURATION: Duration = Duration::from_secs(10);
+/// Enough to see a method's steady speed without downloading the whole video.
+const BENCHMARK_MAX_BYTES: u64 = 10 * 1024 * 1024;
+/// "Me at the zoo": the first YouTube upload, unlikely to ever disappear.
+pub const DIAGNOSTIC_VIDEO_URL: &str = "https://www.youtube.com/watch?v=jNQXAC9IVRw";
+const DIAGNOSTIC_TIMEOUT: Duration = Duration::from_secs(90);
+
+async fn benchmark_method(app: &AppHandle, url: &str, use_aria2c: bool) -> DownloadMethodBenchmark {
+    let method = if use_aria2c { "aria2c" } else { "native" };
+    let failed = |error: String| DownloadMethodBenchmark {
+        method: method.to_string(),
+        average_speed_bytes_per_sec: None,
+        speed_formatted: "N/A".to_string(),
+        samples: 0,
+        error: Some(error),
+    };
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or(0);
+    let temp_dir = std::env::temp_dir()
+        .join("dlpgui_benchmark")
+        .join(format!("{}-{}-{}", method, std::process::id(), nanos));
+    if let Err(err) = std::fs::create_dir_all(&temp_dir) {
+        return failed(format!("Failed to create benchmark directory: {}", err));
+    }
+
+    // Mirror start_download: aria2c works on DASH, the native downloader on HLS.
+    let mut args = base_ytdlp_args(app);
+    args.extend([
+        "--newline".to_string(),
+        "--progress".to_string(),
+        "--no-playlist".to_string(),
+        "--no-part".to_string(),
+        "--extractor-args".to_string(),
+        if use_aria2c {
+            "youtube:skip=hls"
+        } else {
+            "youtube:skip=dash"
+        }
+        .to_string(),
+        "-f".to_string(),
+        "bv*[height<=1080]/b".to_string(),
+        "-P".to_string(),
+        temp_dir.to_string_lossy().to_string(),
+        "-o".to_string(),
+        "benchmark.%(ext)s".to_string(),
+    ]);
+    if use_aria2c {
+        args.push("--downloader".to_string());
+        args.push("aria2c".to_string());
+        args.push("--downloader-args".to_string());
+        args.push(aria2c_downloader_args(&current_settings()));
+    }
+    args.push(url.to_string());
+
+    let sidecar_command = match ytdlp_command(app) {
+        Ok(command) => command,
+        Err(err) => return failed(err.to_string()),
+    };
+    let (mut rx, child) = match sidecar_command.args(args).spawn() {
+        Ok(spawned) => spawned,
+        Err(err) => return failed(err.to_string()),
+    };
+
+    let re_native_speed = Regex::new(r"at\s+([\d.]+\s*[kKMGT]?i?B/s)").unwrap();
+    let re_aria2c_speed = Regex::new(r"DL:([\d.]+[kKMGT]?i?B)").unwrap();
+    let re_native_downloaded =
+        Regex::new(r"\[download\]\s+([\d.]+)%\s+of\s+(~?\s*[\d.]+\s*[kKMGT]?i?B)").unwrap();
+    let re_aria2c_downloaded = Regex::new(r"\[#\w+\s+([\d.]+[kKMGT]?i?B)/").unwrap();
+    let mut speeds: Vec<u64> = Vec::new();
+    let mut last_error = None;
+
+    // Until the first progress line only the overall diagnostic timeout applies.
+    let mut deadline = tokio::time::Instant::now() + DIAGNOSTIC_TIMEOUT;
+    while let Ok(Some(event)) = tokio::time::timeout_at(deadline, rx.recv()).await {
+        match event {
+            CommandEvent::Stdout(line) | CommandEvent::Stderr(line) => {
+                let line_str = String::from_utf8_lossy(&line);
+                let speed = re_native_speed
+                    .captures(&line_str)
+                    .or_else(|| re_aria2c_speed.captures(&line_str))
+                    .and_then(|caps| parse_speed_to_bytes(&caps[1]));
+                if let Some(speed) = speed.filter(|speed| *speed > 0) {
+                    if speeds.is_empty() {
+                        deadline = tokio::time::Instant::now() + BENCHMARK_DURATION;
+                    }
+                    speeds.push(speed);
+                }
+                let downloaded = if let Some(caps) = re_native_downloaded.captures(&line_str) {
+                    caps[1].parse::<f64>().ok().zip(parse_size_to_bytes(&caps[2])).map(
+                        |(percent, total)| (total as f64 * percent / 100.0) as u64,
+                    )
+                } else {
+                    re_aria2c_downloaded
+                        .captures(&line_str)
+                        .and_then(|caps| parse_size_to_bytes(&caps[1]))
+                };
+                if downloaded.is_some_and(|downloaded| downloaded >= BENCHMARK_MAX_BYTES) {
+                    break;
+                }
+                if line_str.starts_with("ERROR:") {
+                    last_error = Some(line_str.trim().to_string());
+                }
+            }
+            CommandEvent::Terminated(_) => break,
+            _ => {}
+        }
+    }
+
+    kill_process_tree(child);
+    // Give the killed process a moment to release its file handles.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let _ = std::fs::remove_dir_all(&temp_dir);
+
+    if speeds.is_empty() {
+        return failed(last_error.unwrap_or_else(|| "No progress reported".to_string()));
+    }
+
+    let average = speeds.iter().sum::<u64>() / speeds.len() as u64;
+    DownloadMethodBenchmark {
+        method: method.to_string(),
+        average_speed_bytes_per_sec: Some(average),
+        speed_formatted: format!("{}/s", format_size(average, false)),
+        samples: speeds.len(),
+        error: None,
+    }
+}
+
+/// Downloads from `url` for a few seconds with each method and compares the
+/// speeds yt-dlp reports. The methods run one after another so they don't
+/// compete for bandwidth.
+#[tauri::command]
+pub async fn benchmark_download_method(
+    app: AppHandle,
+    url: String,
+) -> Result<DownloadBenchmark, String> {
+    let native = benchmark_method(&app, &url, false).await;
+    let aria2c = benchmark_method(&app, &url, true).await;
+
+    let recommended = match (
+        native.average_speed_bytes_per_sec,
+        aria2c.average_speed_bytes_per_sec,
+    ) {
+        (Some(native_speed), Some(aria2c_speed)) => Some(if aria2c_speed > native_speed {
+            "aria2c"
+        } else {
+            "native"
+        }),
+        (Some(_), None) => Some("native"),
+        (None, Some(_)) => Some("aria2c"),
+        (None, None) => None,
+    };
+
+    if recommended.is_none() {
+        return Err(format!(
+            "Benchmark failed for both methods: {}",
+            native.error.clone().unwrap_or_default()
+        ));
+    }
+
+    Ok(DownloadBenchmark {
+        results: vec![native, aria2c],
+        recommended: recommended.map(|method| method.to_string()),
+    })
+}
+
+/// `-version` / `--version` output for ffmpeg and aria2c starts with
+/// "<name> version <version> ...".
+fn parse_tool_version(output: &str, name: &str) -> Option<String> {
+    let first_line = output.lines().next()?.trim();
+    let rest = first_line.strip_prefix(name)?.trim_start();
+    let rest = rest.strip_prefix("version").unwrap_or(rest).trim_start();
+    rest.split_whitespace().next().map(|version| version.to_string())
+}
+
+async fn ytdlp_version(app: &AppHandle) -> Option<String> {
+    let output = ytdlp_command(app)
+        .ok()?
+        .args(vec!["--version"])
+        .output()
+        .await
+        .ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!version.is_empty()).then_some(version)
+}
+
+async fn ffmpeg_version(app: &AppHandle, ffmpeg_path: Option<&str>) -> Option<String> {
+    let command = match ffmpeg_path {
+        Some(path) => app.shell().command(path),
+        None => app.shell().sidecar("ffmpeg").ok()?,
+    };
+    let output = command.args(vec!["-version"]).output().await.ok()?;
+    parse_tool_version(&String::from_utf8_lossy(&output.stdout), "ffmpeg")
+}
+
+/// aria2c isn't bundled; yt-dlp picks it up from PATH, so probe it the same way.
+async fn aria2c_version(app: &AppHandle) -> Option<String> {
+    let output = app
+        .shell()
+        .command("aria2c")
+        .args(vec!["--version"])
+        .output()
+        .await
+        .ok()?;
+    parse_tool_version(&String::from_utf8_lossy(&output.stdout), "aria2")
+}
+
+#[tauri::command]
+pub async fn get_versions(app: AppHandle) -> ToolVersions {
+    let ffmpeg_path = resolve_ffmpeg_path()
+        .ok()
+        .filter(|path| std::path::Path::new(path).exists());
+
+    ToolVersions {
+        app_version: app.package_info().version.to_string(),
+        ytdlp_version: ytdlp_version(&app).await,
+        ffmpeg_version: ffmpeg_version(&app, ffmpeg_path.as_deref()).await,
+        ffmpeg_path,
+        aria2c_version: aria2c_version(&app).await,
+    }
+}
+
+/// Runs a verbose, download-free extraction of a known video with the same
+/// JS runtime and remote-component flags downloads use, and reports which
+/// part of the YouTube setup (JS runtime, challenge solver, network) is failing.
+#[tauri::command]
+pub async fn diagnose_youtube(app: AppHandle) -> Result<YoutubeDiagnostics, String> {
+    let js_runtime = detect_js_runtime(&app).await;
+
+    let mut args = base_ytdlp_args(&app);
+    args.extend([
+        "-v".to_string(),
+        "--simulate".to_string(),
+        "--no-playlist".to_string(),
+        "--print".to_string(),
+        "id".to_string(),
+        DIAGNOSTIC_VIDEO_URL.to_string(),
+    ]);
+    let command = ytdlp_command(&app).map_err(|e| e.to_string())?;
+    let started = Instant::now();
+    let (mut rx, child) = command.args(args).spawn().map_err(|e| e.to_string())?;
+    let deadline = tokio::time::Instant::now() + DIAGNOSTIC_TIMEOUT;
+    let mut stderr_bytes = Vec::new();
+    let mut exit_code = None;
+    loop {
+        match tokio::time::timeout_at(deadline, rx.recv()).await {
+            Ok(Some(CommandEvent::Stderr(line))) => stderr_bytes.extend(line),
+            Ok(Some(CommandEvent::Terminated(payload))) => exit_code = payload.code,
+            Ok(Some(_)) => {}
+            Ok(None) => break,
+            Err(_) => {
+                // Dropping the receiver alone would leave yt-dlp running.
+                kill_process_tree(child);
+                return Err("The test extraction timed out".to_string());
+            }
+        }
+    }
+    let round_trip_ms = started.elapsed().as_millis() as u64;
+
+    let stderr = String::from_utf8_lossy(&stderr_bytes);
+    let re_js_runtimes = Regex::new(r"\[debug\] JS runtimes:\s*(.+)").unwrap();
+    let js_runtimes = re_js_runtimes
+        .captures(&stderr)
+        .map(|caps| caps[1].trim().to_string())
+        .filter(|runtimes| runtimes != "none");
+
+    let re_solver = Regex::new(r"(?i)remote component|challenge solver|\bejs\b|\[jsc").unwrap();
+    let solver_lines: Vec<&str> = stderr.lines().filter(|line| re_solver.is_match(line)).collect();
+    let remote_components_ok = (!solver_lines.is_empty()).then(|| {
+        !solver_lines.iter().any(|line| {
+            let lower = line.to_ascii_lowercase();
+            lower.starts_with("warning:")
+                || lower.starts_with("error:")
+                || lower.contains("failed")
+                || lower.contains("unable")
+        })
+    });
+
+    let warnings = stderr
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("WARNING:"))
+        .map(|warning| warning.trim().to_string())
+        .collect();
+    let extraction_ok = exit_code == Some(0);
+    let error = (!extraction_ok).then(|| classify_ytdlp_error(&stderr, "Test extraction failed"));
+
+    Ok(YoutubeDiagnostics {
+        extraction_ok,
+        error,
+        js_runtime,
+        js_runtimes,
+        remote_components_ok,
+        round_trip_ms,
+        warnings,
+    })
+}
@@ -1,18 +1,55 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 use regex::Regex;
-use tauri::{AppHandle, Emitter};
-use tauri_plugin_shell::{
-    ShellExt,
-    process::CommandEvent,
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_opener::OpenerExt;
+use tauri_plugin_shell::process::CommandEvent;
+use tokio::sync::{Semaphore, oneshot, watch};
+use tokio::task::{JoinHandle, JoinSet};
+
+use crate::args::{
+    aria2c_downloader_args, auth_args, ffmpeg_postprocessor_args, http_chunk_size, redact_args,
+    resolve_output_template, shell_join, validate_extra_args,
 };
-
+use crate::duplicates::{find_possible_duplicate, record_downloaded_video};
+use crate::errors::{CommandError, classify_ytdlp_error, is_disk_full};
+use crate::journal::{record_resumable_download, remove_resumable_download};
+use crate::media::{embed_cover_art, validate_cover_image, verify_output_container};
 use crate::models::{
-    DownloadProgress, FormatsResponse, PlaylistInfo, PlaylistVideo, QualityOption,
+    AggregateEta, AppSettings, AudioQualityOption, AuthCredentials, Chapter, DownloadJob,
+    DownloadOptions, DownloadProgress, ExactSize, FileTimestamp, FormatPreferences,
+    FormatsBatchEntry, FormatsBatchProgress, FormatsResponse, IpVersion, PlayerClient,
+    QualityOption, QualityPreference, ResumableDownload, SubtitleMode,
 };
-use crate::state::ACTIVE_DOWNLOADS;
-
-fn format_size(bytes: u64, is_estimate: bool) -> String {
+use crate::process::{kill_process_tree, run_fetch, run_fetch_limited};
+use crate::queue::remove_queued;
+use crate::ratelimit::{scheduled_rate_limit, set_rate_limit, sets_rate_limit};
+use crate::runtime::{base_ytdlp_args, ytdlp_command};
+use crate::settings::{current_settings, remember_quality};
+use crate::state::{
+    ACTIVE_DOWNLOADS, ACTIVE_PLAYLISTS, CachedVideoJson, DOWNLOAD_QUEUE, DOWNLOAD_STATS,
+    FORMAT_CACHE, KEEP_PARTIAL_DOWNLOADS, PAUSED_DOWNLOADS, RUNNING_DOWNLOADS, RunningDownload,
+};
+use crate::storage::available_space;
+use crate::storyboards::storyboard_options;
+
+/// Each fetch runs its own yt-dlp (and signature solve), so keep this small.
+const FORMATS_BATCH_CONCURRENCY: usize = 3;
+const SUPPORTED_MERGE_FORMATS: &[&str] = &["mp4", "mkv", "webm", "mov"];
+/// Quality sentinel for "highest resolution available", still capped by `max_height`.
+pub const BEST_QUALITY_FORMAT: &str = "best";
+/// Long enough to flip quality/codec preferences without re-fetching, short
+/// enough that signed format URLs are still valid.
+const FORMAT_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+/// `-J` output past this is refused instead of buffered and parsed; even
+/// videos with thousands of formats stay well below it.
+const MAX_VIDEO_JSON_BYTES: usize = 64 * 1024 * 1024;
+
+pub fn format_size(bytes: u64, is_estimate: bool) -> String {
     if bytes == 0 {
         return "Unknown".to_string();
     }
@@ -34,57 +71,970 @@ fn format_size(bytes: u64, is_estimate: bool) -> String {
     }
 }
 
-#[tauri::command]
-pub async fn fetch_formats(
+pub fn parse_size_to_bytes(value: &str) -> Option<u64> {
+    let trimmed = value.trim().trim_start_matches('~').trim();
+    let unit_start = trimmed.find(|c: char| c.is_ascii_alphabetic())?;
+    let number = trimmed[..unit_start].trim().parse::<f64>().ok()?;
+
+    let multiplier = match trimmed[unit_start..].chars().next()?.to_ascii_uppercase() {
+        'B' => 1.0,
+        'K' => 1024.0,
+        'M' => 1024.0 * 1024.0,
+        'G' => 1024.0 * 1024.0 * 1024.0,
+        'T' => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+
+    Some((number * multiplier) as u64)
+}
+
+pub fn parse_speed_to_bytes(value: &str) -> Option<u64> {
+    parse_size_to_bytes(value.trim().trim_end_matches("/s"))
+}
+
+fn format_eta(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+    let secs = seconds % 60;
+
+    if hours > 0 {
+        format!("{:02}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{:02}:{:02}", minutes, secs)
+    }
+}
+
+/// Progress for a live download: nothing is known about the total, so the
+/// event carries the recorded duration instead of a percentage or ETA.
+pub fn live_progress_event(
+    id: &str,
+    downloaded: &str,
+    speed: &str,
+    duration: &str,
+    phase: &str,
+) -> DownloadProgress {
+    let live_duration = if duration.matches(':').count() == 1 {
+        format!("00:{}", duration)
+    } else {
+        duration.to_string()
+    };
+
+    DownloadProgress {
+        id: id.to_string(),
+        percentage: 0.0,
+        size: downloaded.to_string(),
+        speed: speed.to_string(),
+        eta: String::new(),
+        status: "downloading".to_string(),
+        phase: phase.to_string(),
+        downloaded_bytes: parse_size_to_bytes(downloaded),
+        total_bytes: None,
+        speed_bytes_per_sec: parse_speed_to_bytes(speed),
+        indeterminate: true,
+        live_duration: Some(live_duration),
+    }
+}
+
+pub fn progress_event(
+    id: &str,
+    raw_percent: f32,
+    percentage: f32,
+    size: &str,
+    speed: &str,
+    eta: &str,
+    phase: &str,
+) -> DownloadProgress {
+    let total_bytes = parse_size_to_bytes(size);
+    let downloaded_bytes =
+        total_bytes.map(|total| (total as f64 * (raw_percent as f64 / 100.0)) as u64);
+
+    DownloadProgress {
+        id: id.to_string(),
+        percentage,
+        size: size.to_string(),
+        speed: speed.to_string(),
+        eta: eta.to_string(),
+        status: "downloading".to_string(),
+        phase: phase.to_string(),
+        downloaded_bytes,
+        total_bytes,
+        speed_bytes_per_sec: parse_speed_to_bytes(speed),
+        indeterminate: false,
+        live_duration: None,
+    }
+}
+
+const FFMPEG_PROGRESS_FILE: &str = "ffmpeg_progress.txt";
+const DURATION_FILE: &str = "duration.txt";
+/// Where yt-dlp prints each file's path once it reached its final location.
+const FINAL_PATH_FILE: &str = "final_path.txt";
+const FFMPEG_PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Wait before automatic retry N is N times this.
+const AUTO_RETRY_DELAY: Duration = Duration::from_secs(5);
+const DEFAULT_THROTTLE_MIN_SPEED_KBPS: u64 = 100;
+const DEFAULT_THROTTLE_GRACE: Duration = Duration::from_secs(30);
+
+/// Flags a download whose speed stays under `min_speed` for `grace`.
+struct ThrottleDetector {
+    min_speed: u64,
+    grace: Duration,
+    slow_since: Option<Instant>,
+}
+
+impl ThrottleDetector {
+    fn from_settings(settings: &AppSettings) -> Option<Self> {
+        settings.throttle_recovery.then(|| ThrottleDetector {
+            min_speed: settings
+                .throttle_min_speed_kbps
+                .unwrap_or(DEFAULT_THROTTLE_MIN_SPEED_KBPS)
+                * 1024,
+            grace: settings
+                .throttle_grace_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_THROTTLE_GRACE),
+            slow_since: None,
+        })
+    }
+
+    /// Feeds one speed sample; true once the download counts as throttled.
+    fn observe(&mut self, speed_bytes_per_sec: u64) -> bool {
+        if speed_bytes_per_sec >= self.min_speed {
+            self.slow_since = None;
+            return false;
+        }
+        let slow_since = *self.slow_since.get_or_insert_with(Instant::now);
+        slow_since.elapsed() >= self.grace
+    }
+
+    fn reset(&mut self) {
+        self.slow_since = None;
+    }
+}
+
+/// `--newline` prints many progress lines a second; anything faster than
+/// this per download is coalesced before it reaches the webview.
+const PROGRESS_EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Rate-limits `download-progress` for one download. Phase changes and the
+/// 100% update always go through, and the latest suppressed update is sent
+/// before them or by `flush` so the last state shown is never stale.
+#[derive(Default)]
+pub struct ProgressThrottle {
+    last_emit: Option<Instant>,
+    last_phase: String,
+    pending: Option<DownloadProgress>,
+}
+
+impl ProgressThrottle {
+    pub fn emit(&mut self, app: &AppHandle, progress: DownloadProgress) {
+        let due = self
+            .last_emit
+            .is_none_or(|last_emit| last_emit.elapsed() >= PROGRESS_EMIT_INTERVAL);
+        let same_phase = progress.phase == self.last_phase;
+        if !due && same_phase && progress.percentage < 100.0 {
+            self.pending = Some(progress);
+            return;
+        }
+        if same_phase {
+            self.pending = None;
+        } else {
+            self.flush(app);
+        }
+        self.last_emit = Some(Instant::now());
+        self.last_phase.clone_from(&progress.phase);
+        let _ = app.emit("download-progress", progress);
+    }
+
+    /// Sends the update held back since the last emit, if any.
+    pub fn flush(&mut self, app: &AppHandle) {
+        if let Some(progress) = self.pending.take() {
+            self.last_emit = Some(Instant::now());
+            let _ = app.emit("download-progress", progress);
+        }
+    }
+}
+
+/// Locates the bundled ffmpeg next to the executable or in the dev binaries
+/// folder, falling back to the plain name beside the executable.
+pub fn resolve_ffmpeg_path() -> Result<String, String> {
+    let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
+    let exe_dir = exe_path.parent().ok_or("Failed to get exe directory")?;
+    let target = tauri::utils::platform::target_triple().map_err(|e| e.to_string())?;
+    let ffmpeg_exe_with_target = format!("ffmpeg-{}.exe", target);
+    let ffmpeg_exe_simple = "ffmpeg.exe";
+
+    let possible_paths = vec![
+        exe_dir.join(ffmpeg_exe_simple),
+        exe_dir.join(&ffmpeg_exe_with_target),
+        exe_dir.join("binaries").join(ffmpeg_exe_simple),
+        exe_dir.join("binaries").join(&ffmpeg_exe_with_target),
+        PathBuf::from("binaries").join(&ffmpeg_exe_with_target),
+        PathBuf::from("src-tauri/binaries").join(&ffmpeg_exe_with_target),
+    ];
+
+    let mut found_path = None;
+    for path in &possible_paths {
+        if path.exists() {
+            found_path = Some(
+                path.canonicalize()
+                    .unwrap_or_else(|_| path.to_path_buf())
+                    .to_string_lossy()
+                    .to_string(),
+            );
+            break;
+        }
+    }
+
+    Ok(match found_path {
+        Some(path) => path,
+        None => exe_dir.join(ffmpeg_exe_simple).to_string_lossy().to_string(),
+    })
+}
+
+/// yt-dlp buffers ffmpeg's output until it exits, so audio extraction reports
+/// progress through `-progress FILE`; this tails that file and turns
+/// `out_time_us` into a percentage of the video's duration.
+fn spawn_extract_audio_progress(
     app: AppHandle,
-    url: String,
-) -> Result<FormatsResponse, String> {
-    let sidecar_command = app.shell().sidecar("yt-dlp").map_err(|e| e.to_string())?;
+    id: String,
+    temp_dir: PathBuf,
+    stop: Arc<AtomicBool>,
+) {
+    tokio::spawn(async move {
+        let duration = std::fs::read_to_string(temp_dir.join(DURATION_FILE))
+            .ok()
+            .and_then(|contents| contents.lines().last()?.trim().parse::<f64>().ok())
+            .filter(|duration| *duration > 0.0);
+        let Some(duration) = duration else {
+            return;
+        };
+
+        let progress_file = temp_dir.join(FFMPEG_PROGRESS_FILE);
+        while !stop.load(Ordering::Relaxed) {
+            tokio::time::sleep(FFMPEG_PROGRESS_POLL_INTERVAL).await;
+            let Ok(contents) = std::fs::read_to_string(&progress_file) else {
+                continue;
+            };
+
+            let out_time_us = contents
+                .lines()
+                .rev()
+                .find_map(|line| line.strip_prefix("out_time_us="))
+                .and_then(|value| value.trim().parse::<f64>().ok());
+            if let Some(out_time_us) = out_time_us {
+                let percentage = (out_time_us / 1_000_000.0 / duration * 100.0).clamp(0.0, 100.0);
+                let _ = app.emit(
+                    "download-progress",
+                    DownloadProgress {
+                        id: id.clone(),
+                        percentage: percentage as f32,
+                        size: String::new(),
+                        speed: String::new(),
+                        eta: String::new(),
+                        status: "downloading".to_string(),
+                        phase: "extracting audio".to_string(),
+                        downloaded_bytes: None,
+                        total_bytes: None,
+                        speed_bytes_per_sec: None,
+                        indeterminate: false,
+                        live_duration: None,
+                    },
+                );
+            }
+
+            if contents.contains("progress=end") {
+                break;
+            }
+        }
+    });
+}
+
+pub fn record_download_stats(progress: &DownloadProgress) {
+    if let Ok(mut stats) = DOWNLOAD_STATS.lock() {
+        let entry = stats.entry(progress.id.clone()).or_default();
+        if let Some(downloaded) = progress.downloaded_bytes {
+            // Progress running backwards means yt-dlp moved on to the next stream.
+            if downloaded < entry.stream_downloaded_bytes {
+                entry.finished_bytes += entry.stream_total_bytes;
+                entry.stream_total_bytes = 0;
+            }
+            entry.stream_downloaded_bytes = downloaded;
+        }
+        if let Some(total) = progress.total_bytes {
+            entry.stream_total_bytes = total;
+        }
+        entry.total_bytes = entry.finished_bytes + entry.stream_total_bytes;
+        entry.downloaded_bytes = entry.finished_bytes + entry.stream_downloaded_bytes;
+        if let Some(speed) = progress.speed_bytes_per_sec {
+            entry.speed_bytes_per_sec = speed;
+        }
+    }
+}
+
+const DEFAULT_SUBTITLE_LANGS: &str = "en,en-US,en-GB,en-orig";
+
+/// `--sub-langs` value for `options`. A bare code like "de" also matches
+/// its regional and auto-translated variants ("de-DE", "de-orig").
+fn subtitle_languages(options: &DownloadOptions) -> Result<String, String> {
+    let language_re = Regex::new(r"^[A-Za-z]{2,3}(-[A-Za-z0-9]+)*$").unwrap();
+    let requested: Vec<&str> = options
+        .subtitle_languages
+        .iter()
+        .map(|lang| lang.trim())
+        .filter(|lang| !lang.is_empty())
+        .collect();
+    if !requested.is_empty() {
+        let mut langs = Vec::with_capacity(requested.len() + 1);
+        for lang in requested {
+            if !language_re.is_match(lang) {
+                return Err(format!("Invalid subtitle language {:?}", lang));
+            }
+            langs.push(if lang.contains('-') {
+                lang.to_string()
+            } else {
+                format!("{}.*", lang.to_ascii_lowercase())
+            });
+        }
+        langs.push("-live_chat".to_string());
+        return Ok(langs.join(","));
+    }
+
+    let detected = options
+        .video_language
+        .as_deref()
+        .map(str::trim)
+        .filter(|lang| language_re.is_match(lang))
+        .map(|lang| lang.split('-').next().unwrap_or(lang).to_ascii_lowercase());
+
+    Ok(match (options.subtitle_mode, detected) {
+        (SubtitleMode::Auto, Some(lang)) if lang != "en" => {
+            format!("{}.*,{},-live_chat", lang, DEFAULT_SUBTITLE_LANGS)
+        }
+        _ => format!("{},-live_chat", DEFAULT_SUBTITLE_LANGS),
+    })
+}
+
+/// yt-dlp JSON for `url` from any unexpired `fetch_formats` call, whatever
+/// extra args it used.
+pub fn cached_json_for_url(url: &str) -> Option<serde_json::Value> {
+    cached_formats_for_url(url).map(|(json, _)| json)
+}
+
+/// Like `cached_json_for_url`, together with the warnings of that fetch.
+fn cached_formats_for_url(url: &str) -> Option<(serde_json::Value, Vec<String>)> {
+    let suffix = format!("\u{1f}{}", url);
+    let cache = FORMAT_CACHE.lock().ok()?;
+    cache
+        .iter()
+        .find(|(key, cached)| {
+            key.ends_with(&suffix) && cached.fetched_at.elapsed() < FORMAT_CACHE_TTL
+        })
+        .map(|(_, cached)| (cached.json.clone(), cached.warnings.clone()))
+}
+
+/// Drops every cached `-J` result for `url`, whatever args fetched it.
+fn forget_cached_formats(url: &str) {
+    let suffix = format!("\u{1f}{}", url);
+    if let Ok(mut cache) = FORMAT_CACHE.lock() {
+        cache.retain(|key, _| !key.ends_with(&suffix));
+    }
+}
+
+/// Sends the formats `url` offers now as `download-formats`, after the
+/// `FormatUnavailable` error has been shown, so the user can pick again.
+/// Bypasses the cache since that is likely what the stale selection came from.
+async fn emit_current_formats(app: AppHandle, id: String, url: String, options: DownloadOptions) {
+    forget_cached_formats(&url);
+    let formats = fetch_formats(
+        app.clone(),
+        url,
+        None,
+        None,
+        options.auth,
+        Some(options.player_client),
+        None,
+        options.ip_version,
+    )
+    .await;
+    match formats {
+        Ok(formats) => {
+            let _ = app.emit(
+                "download-formats",
+                serde_json::json!({
+                    "id": id,
+                    "formats": formats,
+                }),
+            );
+        }
+        Err(err) => tracing::warn!("Failed to fetch the current formats: {}", err),
+    }
+}
+
+/// Whether the video has (auto) subtitles matching a `--sub-langs` list.
+/// `None` when there is no cached JSON to decide from.
+fn has_requested_subtitles(url: &str, sub_langs: &str) -> Option<bool> {
+    let json = cached_json_for_url(url)?;
+    let patterns: Vec<Regex> = sub_langs
+        .split(',')
+        .map(str::trim)
+        .filter(|lang| !lang.is_empty() && !lang.starts_with('-'))
+        .filter_map(|lang| Regex::new(&format!("^(?:{})$", lang)).ok())
+        .collect();
+
+    let available = ["subtitles", "automatic_captions"].iter().any(|field| {
+        json[*field].as_object().is_some_and(|tracks| {
+            tracks
+                .keys()
+                .any(|lang| patterns.iter().any(|pattern| pattern.is_match(lang)))
+        })
+    });
+    Some(available)
+}
+
+pub fn resolve_extra_args(extra_args: Option<Vec<String>>) -> Result<Vec<String>, String> {
+    let mut resolved = current_settings().default_extra_args;
+    resolved.extend(extra_args.unwrap_or_default());
+    validate_extra_args(&resolved)?;
+    Ok(resolved)
+}
+
+fn file_name_from_path(path: &str) -> &str {
+    path.split(['/', '\\']).next_back().unwrap_or(path)
+}
+
+/// Last path yt-dlp wrote to `FINAL_PATH_FILE` for this download.
+fn last_printed_path(temp_dir: &Path) -> Option<String> {
+    std::fs::read_to_string(temp_dir.join(FINAL_PATH_FILE))
+        .ok()?
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty() && *line != "NA")
+        .map(|line| line.to_string())
+}
+
+/// Path of `path` relative to whichever of `roots` contains it, so files placed
+/// in template subfolders keep their folder when re-joined with the download dir.
+fn output_relative_path(path: &str, roots: &[&Path]) -> String {
+    let path = Path::new(path);
+    roots
+        .iter()
+        .find_map(|root| path.strip_prefix(root).ok())
+        .map(|relative| relative.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_name_from_path(&path.to_string_lossy()).to_string())
+}
 
-    let args = vec![
+/// Moves the separate streams kept by `--keep-video` out of the temp dir,
+/// which is deleted afterwards, into the download dir. Returns where they are.
+fn keep_stream_files(files: &[String], temp_dir: &Path, download_dir: &Path) -> Vec<String> {
+    files
+        .iter()
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let Ok(relative) = path.strip_prefix(temp_dir) else {
+                return Some(path);
+            };
+            let destination = download_dir.join(relative);
+            if let Some(parent) = destination.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            match move_file(&path, &destination) {
+                Ok(()) => Some(destination),
+                Err(err) => {
+                    tracing::warn!("Failed to keep stream {:?}: {}", path, err);
+                    None
+                }
+            }
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .collect()
+}
+
+fn download_path_string(download_dir: &Path, file_name: &str) -> String {
+    download_dir.join(file_name).to_string_lossy().to_string()
+}
+
+/// Image format `--embed-thumbnail` can attach to `container`: mkv keeps the
+/// thumbnail lossless as a png attachment, the mp4 family needs jpg (or png).
+fn thumbnail_format_for(container: &str) -> &'static str {
+    if container == "mkv" { "png" } else { "jpg" }
+}
+
+/// Below this much free space a download of unknown size gets a warning.
+const LOW_DISK_SPACE_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Size of `format_string` from the cached `fetch_formats` JSON: the sum of
+/// its parts when it names format ids, otherwise yt-dlp's estimate for the
+/// default selection.
+fn estimated_download_size(url: &str, format_string: &str) -> Option<u64> {
+    let json = cached_json_for_url(url)?;
+    let size_of = |format: &serde_json::Value| {
+        format["filesize"]
+            .as_u64()
+            .or_else(|| format["filesize_approx"].as_f64().map(|size| size as u64))
+    };
+    let formats = json["formats"].as_array();
+    let by_id = format_string
+        .split('+')
+        .map(|format_id| {
+            formats?
+                .iter()
+                .find(|format| format["format_id"].as_str() == Some(format_id))
+                .and_then(size_of)
+        })
+        .sum::<Option<u64>>();
+    by_id.or_else(|| size_of(&json))
+}
+
+/// Warns before starting when the download folder looks too small for the
+/// download; merges briefly need room for the parts and the output.
+fn warn_if_low_disk_space(
+    app: &AppHandle,
+    id: &str,
+    url: &str,
+    format_string: &str,
+    download_dir: &Path,
+) {
+    let Some(available) = available_space(download_dir) else {
+        return;
+    };
+    let required = estimated_download_size(url, format_string).map(|size| {
+        if format_string.contains('+') {
+            size * 2
+        } else {
+            size
+        }
+    });
+    if available >= required.unwrap_or(LOW_DISK_SPACE_BYTES) {
+        return;
+    }
+
+    let _ = app.emit(
+        "download-status",
+        serde_json::json!({
+            "id": id,
+            "status": "low_disk_space",
+            "message": format!(
+                "Only {} free in {}",
+                format_size(available, false),
+                download_dir.display()
+            ),
+            "path": download_dir.to_string_lossy(),
+            "available_bytes": available,
+            "required_bytes": required,
+        }),
+    );
+}
+
+/// The folder a disk-full failure hit: the one the error names, otherwise
+/// whichever of the temp and download dirs has less room left.
+fn disk_full_dir<'a>(message: &str, temp_dir: &'a Path, download_dir: &'a Path) -> &'a Path {
+    // The temp dir may sit inside the download dir, so it is checked first.
+    if let Some(named) = [temp_dir, download_dir]
+        .into_iter()
+        .find(|dir| message.contains(dir.to_string_lossy().as_ref()))
+    {
+        return named;
+    }
+    let room = |dir: &Path| available_space(dir).unwrap_or(u64::MAX);
+    if room(temp_dir) < room(download_dir) {
+        temp_dir
+    } else {
+        download_dir
+    }
+}
+
+/// Keeps write probes of concurrent downloads in the same folder apart.
+static WRITE_PROBE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Creates `dir` if needed and proves it is writable with a throwaway file,
+/// so permission problems surface before yt-dlp is spawned.
+fn ensure_writable_dir(dir: &Path, label: &str) -> Result<(), CommandError> {
+    let to_error = |err: std::io::Error| {
+        let message = format!("{} {} is not writable: {}", label, dir.display(), err);
+        match err.kind() {
+            std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem => {
+                CommandError::PermissionDenied {
+                    message,
+                    path: Some(dir.to_string_lossy().to_string()),
+                }
+            }
+            _ => CommandError::from(message),
+        }
+    };
+
+    std::fs::create_dir_all(dir).map_err(to_error)?;
+    let probe = dir.join(format!(
+        ".dlpgui_write_test_{}_{}",
+        std::process::id(),
+        WRITE_PROBE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(to_error)?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+fn move_file(source: &Path, destination: &Path) -> std::io::Result<()> {
+    if std::fs::rename(source, destination).is_ok() {
+        return Ok(());
+    }
+
+    // rename() cannot cross volumes, so fall back to copy + delete.
+    std::fs::copy(source, destination)?;
+    std::fs::remove_file(source)
+}
+
+fn move_into_category_folder(
+    download_dir: &Path,
+    file_path: &Path,
+    category: &str,
+) -> Result<Option<PathBuf>, String> {
+    let settings = current_settings();
+    let folder = match settings.category_folders.get(category) {
+        Some(folder) if !folder.trim().is_empty() => PathBuf::from(folder.trim()),
+        _ => return Ok(None),
+    };
+
+    if !folder
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(format!(
+            "Category folder {:?} must be a relative path inside the download directory",
+            folder
+        ));
+    }
+
+    let file_name = file_path
+        .file_name()
+        .ok_or_else(|| format!("Invalid downloaded file path {:?}", file_path))?;
+    let target_dir = download_dir.join(folder);
+    std::fs::create_dir_all(&target_dir)
+        .map_err(|e| format!("Failed to create category folder {:?}: {}", target_dir, e))?;
+
+    let destination = target_dir.join(file_name);
+    move_file(file_path, &destination)
+        .map_err(|e| format!("Failed to move {:?} to {:?}: {}", file_path, destination, e))?;
+
+    Ok(Some(destination))
+}
+
+pub fn clear_download_stats(id: &str) {
+    if let Ok(mut stats) = DOWNLOAD_STATS.lock() {
+        stats.remove(id);
+    }
+}
+
+fn estimate_size(bitrate: f64, duration: f64) -> u64 {
+    if bitrate > 0.0 && duration > 0.0 {
+        ((bitrate * duration / 8.0) * 1024.0 * 0.18) as u64
+    } else {
+        0
+    }
+}
+
+fn direct_filesize(format: &serde_json::Value) -> Option<u64> {
+    format["filesize"]
+        .as_u64()
+        .or_else(|| format["filesize_approx"].as_u64())
+}
+
+/// Builds the `youtube:` extractor-args value, adding the player client to the
+/// skip list when one was chosen.
+fn youtube_extractor_args(skip: &str, player_client: PlayerClient) -> String {
+    match player_client.as_arg() {
+        Some(client) => format!("youtube:skip={};player_client={}", skip, client),
+        None => format!("youtube:skip={}", skip),
+    }
+}
+
+async fn fetch_video_json(
+    app: &AppHandle,
+    url: String,
+    extra_args: Vec<String>,
+    request_id: Option<&str>,
+    player_client: PlayerClient,
+) -> Result<(serde_json::Value, Vec<String>), CommandError> {
+    let sidecar_command = ytdlp_command(app).map_err(|e| e.to_string())?;
+
+    let mut args = base_ytdlp_args(app);
+    args.extend([
         "-J".to_string(),
-        "--no-warnings".to_string(),
-        "--js-runtimes".to_string(),
-        "node".to_string(),
-        "--remote-components".to_string(),
-        "ejs:github".to_string(),
         "--extractor-args".to_string(),
-        "youtube:skip=dash".to_string(),
-        url,
-    ];
+        youtube_extractor_args("dash", player_client),
+    ]);
+    args.extend(extra_args);
+    args.push(url);
 
-    let output = sidecar_command
-        .args(args)
-        .output()
-        .await
-        .map_err(|e| e.to_string())?;
+    let cache_key = args.join("\u{1f}");
+    if let Some(cached) = cached_video_json(&cache_key) {
+        tracing::debug!("Reusing cached yt-dlp JSON");
+        return Ok(cached);
+    }
+    tracing::debug!("yt-dlp args: {:?}", redact_args(&args));
+
+    // Raw chunks: the single-line JSON isn't split and re-buffered line by line.
+    let command = sidecar_command.set_raw_out(true).args(args);
+    let output = run_fetch_limited(command, request_id, MAX_VIDEO_JSON_BYTES).await?;
 
-    if !output.status.success() {
+    if output.stdout_overflow {
+        return Err(format!(
+            "yt-dlp's info for this URL is larger than {} MB, too much to load",
+            MAX_VIDEO_JSON_BYTES / (1024 * 1024)
+        )
+        .into());
+    }
+    if !output.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to fetch formats: {}", stderr));
+        return Err(classify_ytdlp_error(&stderr, "Failed to fetch formats"));
     }
 
-    let json_str = String::from_utf8_lossy(&output.stdout);
-    let json: serde_json::Value =
-        serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| CommandError::from(format!("Failed to parse JSON: {}", e)))?;
 
-    let formats = json["formats"].as_array().ok_or("No formats found")?;
-    let duration = json["duration"].as_f64().unwrap_or(0.0);
+    // Warnings explain partial results, e.g. DASH withheld so only 720p shows up.
+    let mut warnings: Vec<String> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        if let Some(warning) = line.trim().strip_prefix("WARNING:") {
+            let warning = warning.trim().to_string();
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
 
-    let estimate_size = |bitrate: f64, dur: f64| -> u64 {
-        if bitrate > 0.0 && dur > 0.0 {
-            ((bitrate * dur / 8.0) * 1024.0 * 0.18) as u64
-        } else {
-            0
+    if let Ok(mut cache) = FORMAT_CACHE.lock() {
+        cache.retain(|_, cached| cached.fetched_at.elapsed() < FORMAT_CACHE_TTL);
+        cache.insert(
+            cache_key,
+            CachedVideoJson {
+                fetched_at: Instant::now(),
+                json: json.clone(),
+                warnings: warnings.clone(),
+            },
+        );
+    }
+
+    Ok((json, warnings))
+}
+
+fn cached_video_json(cache_key: &str) -> Option<(serde_json::Value, Vec<String>)> {
+    let cache = FORMAT_CACHE.lock().ok()?;
+    let cached = cache.get(cache_key)?;
+    (cached.fetched_at.elapsed() < FORMAT_CACHE_TTL)
+        .then(|| (cached.json.clone(), cached.warnings.clone()))
+}
+
+/// Resolves `format_string` against `url` without downloading anything and
+/// returns the selected format id(s), e.g. "137+140".
+#[tauri::command]
+pub async fn validate_format_string(
+    app: AppHandle,
+    url: String,
+    format_string: String,
+    request_id: Option<String>,
+    auth: Option<AuthCredentials>,
+) -> Result<String, CommandError> {
+    let (format_id, _) =
+        resolve_format(&app, &url, &format_string, request_id.as_deref(), auth.as_ref()).await?;
+    Ok(format_id)
+}
+
+/// The format id(s) `format_string` selects for `url` and the height of the
+/// result, `None` for audio.
+async fn resolve_format(
+    app: &AppHandle,
+    url: &str,
+    format_string: &str,
+    request_id: Option<&str>,
+    auth: Option<&AuthCredentials>,
+) -> Result<(String, Option<u32>), CommandError> {
+    let format_string = format_string.trim();
+    if format_string.is_empty() {
+        return Err("Format string cannot be empty".into());
+    }
+
+    let mut args = base_ytdlp_args(app);
+    args.extend([
+        "--simulate".to_string(),
+        "--no-playlist".to_string(),
+        "-f".to_string(),
+        format_string.to_string(),
+        "--print".to_string(),
+        "%(format_id)s\t%(height)s".to_string(),
+    ]);
+    args.extend(resolve_extra_args(None)?);
+    if let Some(auth) = auth {
+        args.extend(auth_args(auth));
+    }
+    args.push(url.to_string());
+    tracing::debug!("yt-dlp args: {:?}", redact_args(&args));
+
+    let sidecar_command = ytdlp_command(app).map_err(|e| e.to_string())?;
+    let output = run_fetch(sidecar_command.args(args), request_id).await?;
+    if !output.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(classify_ytdlp_error(&stderr, "Format selection failed"));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .ok_or("Format selection resolved to nothing")?;
+    let (format_id, height) = line.split_once('\t').unwrap_or((line, "NA"));
+    Ok((format_id.to_string(), height.trim().parse().ok()))
+}
+
+/// Size yt-dlp reports for `format_id` (a single id or a "video+audio"
+/// combo), summing the components of merged selections.
+#[tauri::command]
+pub async fn get_exact_size(
+    app: AppHandle,
+    url: String,
+    format_id: String,
+    auth: Option<AuthCredentials>,
+) -> Result<ExactSize, CommandError> {
+    let format_id = format_id.trim();
+    if format_id.is_empty() {
+        return Err("Format id cannot be empty".into());
+    }
+
+    let mut args = base_ytdlp_args(&app);
+    args.extend([
+        "--simulate".to_string(),
+        "--no-playlist".to_string(),
+        "-f".to_string(),
+        format_id.to_string(),
+    ]);
+    // One line each; merged selections only have sizes on their components.
+    for template in [
+        "%(filesize)s",
+        "%(filesize_approx)s",
+        "%(requested_formats.:.filesize)j",
+        "%(requested_formats.:.filesize_approx)j",
+    ] {
+        args.push("--print".to_string());
+        args.push(template.to_string());
+    }
+    args.extend(resolve_extra_args(None)?);
+    if let Some(auth) = auth.as_ref() {
+        args.extend(auth_args(auth));
+    }
+    args.push(url);
+    tracing::debug!("yt-dlp args: {:?}", redact_args(&args));
+
+    let sidecar_command = ytdlp_command(&app).map_err(|e| e.to_string())?;
+    let output = run_fetch(sidecar_command.args(args), None).await?;
+    if !output.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(classify_ytdlp_error(&stderr, "Size lookup failed"));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().map(str::trim).collect();
+    let line = |index: usize| lines.get(index).copied().unwrap_or("NA");
+    let number = |value: &str| value.parse::<f64>().ok().filter(|size| *size > 0.0);
+    let components = |value: &str| -> Option<Vec<Option<f64>>> {
+        let values: Vec<serde_json::Value> = serde_json::from_str(value).ok()?;
+        Some(values.iter().map(|value| value.as_f64().filter(|size| *size > 0.0)).collect())
+    };
+
+    let (filesize, filesize_approx) = (number(line(0)), number(line(1)));
+    let exact_parts = components(line(2));
+    let approx_parts = components(line(3));
+    let summed_parts = exact_parts.as_ref().and_then(|exact_parts| {
+        let mut all_exact = true;
+        let mut total = 0.0;
+        for (index, exact) in exact_parts.iter().enumerate() {
+            let approx = approx_parts
+                .as_ref()
+                .and_then(|approx_parts| approx_parts.get(index).copied().flatten());
+            all_exact &= exact.is_some();
+            total += exact.or(approx)?;
         }
+        Some((total, all_exact))
+    });
+
+    let (size, exact) = match (summed_parts, filesize, filesize_approx) {
+        (Some((total, all_exact)), _, _) => (Some(total as u64), all_exact),
+        (None, Some(size), _) => (Some(size as u64), true),
+        (None, None, Some(size)) => (Some(size as u64), false),
+        (None, None, None) => (None, false),
+    };
+    Ok(ExactSize {
+        size,
+        exact,
+        size_formatted: size
+            .map_or_else(|| "Unknown".to_string(), |size| format_size(size, !exact)),
+    })
+}
+
+/// Codec names users pick, mapped to the prefixes yt-dlp reports.
+const CODEC_ALIASES: &[(&str, &[&str])] = &[
+    ("av1", &["av01"]),
+    ("vp9", &["vp09", "vp9"]),
+    ("h264", &["avc1", "avc", "h264"]),
+    ("h265", &["hvc1", "hev1", "hevc", "h265"]),
+    ("aac", &["mp4a", "aac"]),
+];
+
+/// Whether `codec` belongs to the `preferred` family; no preference matches all.
+fn codec_matches(codec: Option<&str>, preferred: Option<&str>) -> bool {
+    let Some(preferred) = preferred.map(|codec| codec.trim().to_ascii_lowercase()) else {
+        return true;
     };
+    if preferred.is_empty() {
+        return true;
+    }
+    let codec = codec.unwrap_or("").to_ascii_lowercase();
+    let prefixes = CODEC_ALIASES
+        .iter()
+        .find(|(name, prefixes)| *name == preferred || prefixes.contains(&preferred.as_str()))
+        .map(|(_, prefixes)| prefixes.to_vec())
+        .unwrap_or_else(|| vec![preferred.as_str()]);
+    prefixes.iter().any(|prefix| codec.starts_with(prefix))
+}
+
+/// The formats satisfying `matches`, or all of them when none do.
+fn prefer_matching(
+    formats: Vec<&serde_json::Value>,
+    matches: impl Fn(&serde_json::Value) -> bool,
+) -> Vec<&serde_json::Value> {
+    let matching: Vec<&serde_json::Value> =
+        formats.iter().copied().filter(|format| matches(format)).collect();
+    if matching.is_empty() {
+        formats
+    } else {
+        matching
+    }
+}
+
+/// Turns `-J` output into the quality list, picking per height the best
+/// stream that matches `preferences` (falling back to any stream when none do).
+fn build_formats_response(
+    json: &serde_json::Value,
+    warnings: Vec<String>,
+    preferences: &FormatPreferences,
+) -> Result<FormatsResponse, CommandError> {
+    let formats = json["formats"].as_array().ok_or("No formats found")?;
+    let duration = json["duration"].as_f64().unwrap_or(0.0);
+    let duration_known = duration > 0.0;
 
     let mut best_audio_size = 0u64;
     let mut best_audio_format_id = String::new();
     let mut best_audio_bitrate = 0.0;
     let mut best_audio_is_estimated = false;
 
-    for format in formats {
+    let audio_formats = prefer_matching(formats.iter().collect(), |format| {
+        codec_matches(format["acodec"].as_str(), preferences.audio_codec.as_deref())
+    });
+    for format in audio_formats {
         let vcodec = format["vcodec"].as_str().unwrap_or("none");
         let acodec = format["acodec"].as_str().unwrap_or("none");
 
@@ -93,16 +1043,18 @@ pub async fn fetch_formats(
             let tbr = format["tbr"].as_f64().unwrap_or(0.0);
             let audio_br = if abr > 0.0 { abr } else { tbr };
 
-            let direct_size = format["filesize"]
-                .as_u64()
-                .or_else(|| format["filesize_approx"].as_u64());
+            let direct_size = direct_filesize(format);
             let (size, is_estimated) = if let Some(value) = direct_size {
                 (value, false)
             } else {
                 (estimate_size(audio_br, duration), true)
             };
 
-            if audio_br > best_audio_bitrate || (audio_br == 0.0 && size > best_audio_size) {
+            let fills_unknown_size = !duration_known && best_audio_size == 0 && size > 0;
+            if audio_br > best_audio_bitrate
+                || (audio_br == 0.0 && size > best_audio_size)
+                || fills_unknown_size
+            {
                 best_audio_bitrate = audio_br;
                 best_audio_size = size;
                 best_audio_format_id = format["format_id"].as_str().unwrap_or("").to_string();
@@ -117,63 +1069,120 @@ pub async fn fetch_formats(
     for target_height in target_heights {
         let mut best_video_for_height: Option<&serde_json::Value> = None;
         let mut best_vbr = 0.0;
+        let mut best_sized_for_height: Option<&serde_json::Value> = None;
+        let mut best_sized_vbr = 0.0;
+
+        let height_formats: Vec<&serde_json::Value> = formats
+            .iter()
+            .filter(|format| {
+                let height = format["height"].as_i64().unwrap_or(0) as i32;
+                let vcodec = format["vcodec"].as_str().unwrap_or("none");
+                height == target_height && vcodec != "none" && !vcodec.is_empty()
+            })
+            .collect();
+        let height_formats = prefer_matching(height_formats, |format| {
+            codec_matches(format["vcodec"].as_str(), preferences.video_codec.as_deref())
+        });
+        let height_formats = prefer_matching(height_formats, |format| {
+            match (preferences.max_fps, format["fps"].as_f64()) {
+                (Some(max_fps), Some(fps)) => fps <= f64::from(max_fps) + 0.5,
+                _ => true,
+            }
+        });
+        let height_formats = prefer_matching(height_formats, |format| {
+            let acodec = format["acodec"].as_str().unwrap_or("none");
+            !preferences.prefer_progressive || (acodec != "none" && !acodec.is_empty())
+        });
+
+        for format in height_formats {
+            let vbr = format["vbr"].as_f64().unwrap_or(0.0);
+            let tbr = format["tbr"].as_f64().unwrap_or(0.0);
+            let bitrate = if vbr > 0.0 { vbr } else { tbr };
 
-        for format in formats {
-            let height = format["height"].as_i64().unwrap_or(0) as i32;
-            let vcodec = format["vcodec"].as_str().unwrap_or("none");
+            if best_video_for_height.is_none() || bitrate > best_vbr {
+                best_video_for_height = Some(format);
+                best_vbr = bitrate;
+            }
 
-            if height == target_height && vcodec != "none" && !vcodec.is_empty() {
-                let vbr = format["vbr"].as_f64().unwrap_or(0.0);
-                let tbr = format["tbr"].as_f64().unwrap_or(0.0);
-                let bitrate = if vbr > 0.0 { vbr } else { tbr };
+            if direct_filesize(format).is_some()
+                && (best_sized_for_height.is_none() || bitrate > best_sized_vbr)
+            {
+                best_sized_for_height = Some(format);
+                best_sized_vbr = bitrate;
+            }
+        }
 
-                if best_video_for_height.is_none() || bitrate > best_vbr {
-                    best_video_for_height = Some(format);
-                    best_vbr = bitrate;
-                }
+        // Without a duration the bitrate heuristic yields nothing, so a format
+        // that reports its own filesize is the only way to show a real size.
+        if !duration_known {
+            let best_has_size =
+                best_video_for_height.is_some_and(|format| direct_filesize(format).is_some());
+            if !best_has_size && best_sized_for_height.is_some() {
+                best_video_for_height = best_sized_for_height;
             }
         }
 
         if let Some(video_format) = best_video_for_height {
             let format_id = video_format["format_id"].as_str().unwrap_or("").to_string();
+            let fps = video_format["fps"].as_f64().filter(|fps| *fps > 0.0);
+            let vcodec = video_format["vcodec"]
+                .as_str()
+                .and_then(|codec| codec.split('.').next())
+                .filter(|codec| !codec.is_empty() && *codec != "none")
+                .map(|codec| codec.to_string());
             let acodec = video_format["acodec"].as_str().unwrap_or("none");
             let has_audio = acodec != "none" && !acodec.is_empty();
             let vbr = video_format["vbr"].as_f64().unwrap_or(0.0);
             let tbr = video_format["tbr"].as_f64().unwrap_or(0.0);
             let video_bitrate = if vbr > 0.0 { vbr } else { tbr };
 
-            let direct_size = video_format["filesize"]
-                .as_u64()
-                .or_else(|| video_format["filesize_approx"].as_u64());
+            let direct_size = direct_filesize(video_format);
             let (video_size, video_is_estimated) = if let Some(size) = direct_size {
                 (size, false)
             } else {
                 (estimate_size(video_bitrate, duration), true)
             };
 
-            let (audio_size, total_size, format_string, is_estimated) = if has_audio {
+            let (audio_size, audio_is_estimated, format_string) = if has_audio {
                 (
                     0,
-                    video_size,
-                    format!(
-                        "(bv*[height={}]+ba)/b[height={}]/b[height<={}]",
-                        target_height, target_height, target_height
-                    ),
-                    video_is_estimated,
+                    false,
+                    if preferences.prefer_progressive {
+                        format!(
+                            "b[height={}]/b[height<={}]/(bv*[height<={}]+ba)",
+                            target_height, target_height, target_height
+                        )
+                    } else {
+                        format!(
+                            "(bv*[height={}]+ba)/b[height={}]/b[height<={}]",
+                            target_height, target_height, target_height
+                        )
+                    },
                 )
             } else {
                 (
                     best_audio_size,
-                    video_size + best_audio_size,
+                    best_audio_is_estimated,
                     if !best_audio_format_id.is_empty() {
                         format!("({}+{})/best", format_id, best_audio_format_id)
                     } else {
                         format!("(bv*[height<={}]+ba)/b[height<={}]", target_height, target_height)
                     },
-                    video_is_estimated || best_audio_is_estimated,
                 )
             };
 
+            // The total is only exact when every component is a real filesize;
+            // otherwise keep the split so the UI can show "12 MB + ~4 MB".
+            let total_size = video_size + audio_size;
+            let is_estimated = video_is_estimated || audio_is_estimated;
+            let size_breakdown = (!has_audio && video_size > 0 && audio_size > 0).then(|| {
+                format!(
+                    "{} + {}",
+                    format_size(video_size, video_is_estimated),
+                    format_size(audio_size, audio_is_estimated)
+                )
+            });
+
             qualities.push(QualityOption {
                 quality: format!("{}p", target_height),
                 height: target_height,
@@ -184,6 +1193,13 @@ pub async fn fetch_formats(
                 format_string,
                 has_combined_audio: has_audio,
                 available: true,
+                video_size_estimated: video_is_estimated,
+                audio_size_estimated: audio_is_estimated,
+                total_size_estimated: is_estimated,
+                size_breakdown,
+                fps,
+                vcodec,
+                delivered_height: Some(target_height),
             });
         } else {
             qualities.push(QualityOption {
@@ -199,95 +1215,496 @@ pub async fn fetch_formats(
                 ),
                 has_combined_audio: false,
                 available: false,
+                video_size_estimated: false,
+                audio_size_estimated: false,
+                total_size_estimated: false,
+                size_breakdown: None,
+                fps: None,
+                vcodec: None,
+                delivered_height: None,
             });
         }
     }
 
-    qualities.sort_by(|a, b| b.height.cmp(&a.height));
+    // An empty bucket downloads the nearest height the video does have rather
+    // than whatever `height<=N` reaches, so "720p" can't silently mean 480p.
+    // Heights compare by ratio, ties going to the higher one.
+    let max_height = current_settings().max_height;
+    let fallbacks: Vec<QualityOption> = qualities
+        .iter()
+        .filter(|quality| quality.available)
+        .filter(|quality| max_height.is_none_or(|max| i64::from(quality.height) <= i64::from(max)))
+        .cloned()
+        .collect();
+    for quality in qualities.iter_mut().filter(|quality| !quality.available) {
+        let distance = |option: &QualityOption| {
+            (f64::from(option.height) / f64::from(quality.height)).ln().abs()
+        };
+        let nearest = fallbacks.iter().min_by(|a, b| {
+            distance(a)
+                .total_cmp(&distance(b))
+                .then(b.height.cmp(&a.height))
+        });
+        if let Some(nearest) = nearest {
+            *quality = QualityOption {
+                quality: quality.quality.clone(),
+                height: quality.height,
+                available: false,
+                ..nearest.clone()
+            };
+        }
+    }
+
+    if let Some(max_height) = max_height {
+        for quality in qualities
+            .iter_mut()
+            .filter(|quality| i64::from(quality.height) > i64::from(max_height))
+        {
+            quality.available = false;
+        }
+    }
+
+    let mut qualities = dedup_qualities(qualities);
+    qualities.sort_by_key(|quality| std::cmp::Reverse(quality.height));
+    if let Some(best) = best_available_quality(
+        formats,
+        duration,
+        best_audio_size,
+        best_audio_is_estimated,
+    ) {
+        qualities.insert(0, best);
+    }
 
     Ok(FormatsResponse {
         qualities,
         best_audio_size,
         best_audio_format_id,
+        duration_known,
+        language: json["language"].as_str().map(|lang| lang.to_string()),
+        warnings,
+        storyboards: storyboard_options(formats),
+    })
+}
+
+/// Synthetic "Best available" entry for `BEST_QUALITY_FORMAT`, sized from the
+/// highest video stream under `max_height`, which can exceed the fixed heights.
+fn best_available_quality(
+    formats: &[serde_json::Value],
+    duration: f64,
+    best_audio_size: u64,
+    best_audio_is_estimated: bool,
+) -> Option<QualityOption> {
+    let max_height = current_settings().max_height.map(i64::from);
+    let bitrate = |format: &serde_json::Value| {
+        let vbr = format["vbr"].as_f64().unwrap_or(0.0);
+        if vbr > 0.0 { vbr } else { format["tbr"].as_f64().unwrap_or(0.0) }
+    };
+    let video_format = formats
+        .iter()
+        .filter(|format| {
+            let vcodec = format["vcodec"].as_str().unwrap_or("none");
+            let height = format["height"].as_i64().unwrap_or(0);
+            vcodec != "none"
+                && !vcodec.is_empty()
+                && height > 0
+                && max_height.is_none_or(|max_height| height <= max_height)
+        })
+        .max_by(|a, b| {
+            let rank = |format: &serde_json::Value| {
+                (format["height"].as_i64().unwrap_or(0), format["fps"].as_f64().unwrap_or(0.0))
+            };
+            rank(a)
+                .partial_cmp(&rank(b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(bitrate(a).total_cmp(&bitrate(b)))
+        })?;
+
+    let height = video_format["height"].as_i64().unwrap_or(0) as i32;
+    let acodec = video_format["acodec"].as_str().unwrap_or("none");
+    let has_audio = acodec != "none" && !acodec.is_empty();
+    let (video_size, video_is_estimated) = match direct_filesize(video_format) {
+        Some(size) => (size, false),
+        None => (estimate_size(bitrate(video_format), duration), true),
+    };
+    let (audio_size, audio_is_estimated) = if has_audio {
+        (0, false)
+    } else {
+        (best_audio_size, best_audio_is_estimated)
+    };
+    let total_size = video_size + audio_size;
+    let is_estimated = video_is_estimated || audio_is_estimated;
+
+    Some(QualityOption {
+        quality: "Best available".to_string(),
+        height,
+        video_size,
+        audio_size,
+        total_size,
+        total_size_formatted: format_size(total_size, is_estimated),
+        format_string: BEST_QUALITY_FORMAT.to_string(),
+        has_combined_audio: has_audio,
+        available: true,
+        video_size_estimated: video_is_estimated,
+        audio_size_estimated: audio_is_estimated,
+        total_size_estimated: is_estimated,
+        size_breakdown: (!has_audio && video_size > 0 && audio_size > 0).then(|| {
+            format!(
+                "{} + {}",
+                format_size(video_size, video_is_estimated),
+                format_size(audio_size, audio_is_estimated)
+            )
+        }),
+        fps: video_format["fps"].as_f64().filter(|fps| *fps > 0.0),
+        vcodec: video_format["vcodec"]
+            .as_str()
+            .and_then(|codec| codec.split('.').next())
+            .filter(|codec| !codec.is_empty() && *codec != "none")
+            .map(|codec| codec.to_string()),
+        delivered_height: Some(height),
     })
 }
 
+/// Drops every cached `-J` result; returns how many entries were removed.
+#[tauri::command]
+pub fn clear_format_cache() -> usize {
+    FORMAT_CACHE
+        .lock()
+        .map(|mut cache| {
+            let count = cache.len();
+            cache.clear();
+            count
+        })
+        .unwrap_or(0)
+}
+
 #[tauri::command]
-pub async fn fetch_playlist_info(
+#[allow(clippy::too_many_arguments)]
+pub async fn fetch_formats(
     app: AppHandle,
     url: String,
-) -> Result<PlaylistInfo, String> {
-    let sidecar_command = app.shell().sidecar("yt-dlp").map_err(|e| e.to_string())?;
-    let args = vec![
-        "-J".to_string(),
-        "--flat-playlist".to_string(),
-        "--no-warnings".to_string(),
+    extra_args: Option<Vec<String>>,
+    request_id: Option<String>,
+    auth: Option<AuthCredentials>,
+    player_client: Option<PlayerClient>,
+    preferences: Option<FormatPreferences>,
+    ip_version: Option<IpVersion>,
+) -> Result<FormatsResponse, CommandError> {
+    let mut extra_args = resolve_extra_args(extra_args)?;
+    let ip_version = ip_version.unwrap_or(current_settings().ip_version);
+    extra_args.extend(ip_version.as_arg().map(str::to_string));
+    if let Some(auth) = auth.as_ref() {
+        extra_args.extend(auth_args(auth));
+    }
+    let (json, warnings) = fetch_video_json(
+        &app,
         url,
-    ];
+        extra_args,
+        request_id.as_deref(),
+        player_client.unwrap_or_default(),
+    )
+    .await?;
 
-    let output = sidecar_command
-        .args(args)
-        .output()
-        .await
-        .map_err(|e| e.to_string())?;
+    build_formats_response(&json, warnings, &preferences.unwrap_or_default())
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to fetch playlist info: {}", stderr));
-    }
-
-    let json_str = String::from_utf8_lossy(&output.stdout);
-    let json: serde_json::Value =
-        serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse JSON: {}", e))?;
-
-    let title = json["title"]
-        .as_str()
-        .unwrap_or("Unknown Playlist")
-        .to_string();
-    let channel = json["channel"]
-        .as_str()
-        .or_else(|| json["uploader"].as_str())
-        .unwrap_or("Unknown Channel")
-        .to_string();
-    let description = json["description"].as_str().unwrap_or("").to_string();
-
-    let entries: Vec<PlaylistVideo> = json["entries"]
-        .as_array()
-        .map(|array| {
-            array
-                .iter()
-                .filter_map(|entry| {
-                    let id = entry["id"].as_str()?.to_string();
-                    let video_title = entry["title"]
-                        .as_str()
-                        .unwrap_or("Unknown Video")
-                        .to_string();
-                    let video_url = entry["url"]
-                        .as_str()
-                        .map(|url| url.to_string())
-                        .unwrap_or_else(|| format!("https://www.youtube.com/watch?v={}", id));
-
-                    Some(PlaylistVideo {
-                        id,
-                        title: video_title,
-                        url: video_url,
-                        duration: entry["duration"].as_f64(),
-                    })
-                })
-                .collect()
+/// Recomputes the quality list for an already fetched `url` under new
+/// preferences, straight from the cached `-J` output.
+#[tauri::command]
+pub fn reselect_quality(
+    url: String,
+    preferences: Option<FormatPreferences>,
+) -> Result<FormatsResponse, CommandError> {
+    let (json, warnings) = cached_formats_for_url(&url)
+        .ok_or("Formats for this URL are no longer cached; fetch them again")?;
+    build_formats_response(&json, warnings, &preferences.unwrap_or_default())
+}
+
+/// Chapters of `url` from its `-J` JSON, reusing a cached fetch when there
+/// is one. Videos without chapters give an empty list.
+#[tauri::command]
+pub async fn fetch_chapters(
+    app: AppHandle,
+    url: String,
+    request_id: Option<String>,
+    auth: Option<AuthCredentials>,
+    player_client: Option<PlayerClient>,
+) -> Result<Vec<Chapter>, CommandError> {
+    let json = match cached_json_for_url(&url) {
+        Some(json) => json,
+        None => {
+            let mut extra_args = resolve_extra_args(None)?;
+            if let Some(auth) = auth.as_ref() {
+                extra_args.extend(auth_args(auth));
+            }
+            fetch_video_json(
+                &app,
+                url,
+                extra_args,
+                request_id.as_deref(),
+                player_client.unwrap_or_default(),
+            )
+            .await?
+            .0
+        }
+    };
+    let duration = json["duration"].as_f64();
+
+    let Some(chapters) = json["chapters"].as_array() else {
+        return Ok(Vec::new());
+    };
+    Ok(chapters
+        .iter()
+        .enumerate()
+        .filter_map(|(index, chapter)| {
+            let start_time = chapter["start_time"].as_f64()?;
+            // The last chapter sometimes has no end; it runs to the end of the video.
+            let end_time = chapter["end_time"].as_f64().or(duration)?;
+            let title = chapter["title"]
+                .as_str()
+                .filter(|title| !title.trim().is_empty())
+                .map(|title| title.to_string())
+                .unwrap_or_else(|| format!("Chapter {}", index + 1));
+            Some(Chapter {
+                title,
+                start_time,
+                end_time,
+            })
         })
-        .unwrap_or_default();
+        .collect())
+}
 
-    Ok(PlaylistInfo {
-        video_count: entries.len(),
-        title,
-        channel,
-        description,
-        entries,
-    })
+/// `--download-sections` ranges for the picked chapters, with back-to-back
+/// chapters merged so they come out as one file.
+fn chapter_sections(chapters: &[Chapter]) -> Result<Vec<String>, String> {
+    let mut ranges: Vec<(f64, f64)> = Vec::with_capacity(chapters.len());
+    for chapter in chapters {
+        if chapter.start_time < 0.0 || chapter.end_time <= chapter.start_time {
+            return Err(format!(
+                "Chapter {:?} has an invalid time range {}-{}",
+                chapter.title, chapter.start_time, chapter.end_time
+            ));
+        }
+        ranges.push((chapter.start_time, chapter.end_time));
+    }
+    ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut merged: Vec<(f64, f64)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            // Chapter times are rounded, so allow a little slack between them.
+            Some(last) if start <= last.1 + 0.5 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    Ok(merged
+        .into_iter()
+        .map(|(start, end)| format!("*{}-{}", start, end))
+        .collect())
+}
+
+/// Collapses available rows with the same delivered height, frame rate and
+/// codec into one, keeping the larger (higher bitrate) video stream in the
+/// first row's position. Unavailable rows stay, so an empty bucket can show
+/// the nearest height it would deliver instead.
+fn dedup_qualities(qualities: Vec<QualityOption>) -> Vec<QualityOption> {
+    let same_quality = |a: &QualityOption, b: &QualityOption| {
+        a.available
+            && b.available
+            && a.delivered_height.is_some()
+            && a.delivered_height == b.delivered_height
+            && a.fps.map(f64::round) == b.fps.map(f64::round)
+            && a.vcodec == b.vcodec
+    };
+
+    let mut deduped: Vec<QualityOption> = Vec::with_capacity(qualities.len());
+    for quality in qualities {
+        match deduped.iter_mut().find(|kept| same_quality(kept, &quality)) {
+            Some(kept) => {
+                if quality.video_size > kept.video_size {
+                    *kept = quality;
+                }
+            }
+            None => deduped.push(quality),
+        }
+    }
+    deduped
+}
+
+/// Runs `fetch_formats` for several URLs, at most `FORMATS_BATCH_CONCURRENCY`
+/// at a time, emitting `formats-batch-progress` as each one resolves. Each
+/// fetch is cancellable as `"{request_id}:{url}"`.
+#[tauri::command]
+pub async fn fetch_formats_batch(
+    app: AppHandle,
+    urls: Vec<String>,
+    extra_args: Option<Vec<String>>,
+    request_id: Option<String>,
+    auth: Option<AuthCredentials>,
+    player_client: Option<PlayerClient>,
+) -> Result<HashMap<String, FormatsBatchEntry>, CommandError> {
+    let mut unique_urls: Vec<String> = Vec::new();
+    for url in urls.into_iter().map(|url| url.trim().to_string()) {
+        if !url.is_empty() && !unique_urls.contains(&url) {
+            unique_urls.push(url);
+        }
+    }
+    // Fail fast on bad args instead of once per URL.
+    resolve_extra_args(extra_args.clone())?;
+
+    let total = unique_urls.len();
+    let semaphore = Arc::new(Semaphore::new(FORMATS_BATCH_CONCURRENCY));
+    let mut tasks = JoinSet::new();
+    let mut task_urls = HashMap::new();
+    for url in unique_urls {
+        let app = app.clone();
+        let extra_args = extra_args.clone();
+        let fetch_request_id = request_id
+            .as_ref()
+            .map(|request_id| format!("{}:{}", request_id, url));
+        let auth = auth.clone();
+        let semaphore = semaphore.clone();
+        let task_url = url.clone();
+        let task = tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            fetch_formats(
+                app,
+                url,
+                extra_args,
+                fetch_request_id,
+                auth,
+                player_client,
+                None,
+                None,
+            )
+            .await
+        });
+        task_urls.insert(task.id(), task_url);
+    }
+
+    let mut results = HashMap::new();
+    while let Some(joined) = tasks.join_next_with_id().await {
+        let (url, result) = match joined {
+            Ok((task_id, result)) => (task_urls.remove(&task_id), result),
+            Err(err) => (
+                task_urls.remove(&err.id()),
+                Err(CommandError::from(format!("Format fetch task failed: {}", err))),
+            ),
+        };
+        let Some(url) = url else {
+            continue;
+        };
+        let entry = match result {
+            Ok(formats) => FormatsBatchEntry {
+                formats: Some(formats),
+                error: None,
+            },
+            Err(error) => FormatsBatchEntry {
+                formats: None,
+                error: Some(error),
+            },
+        };
+        let _ = app.emit(
+            "formats-batch-progress",
+            FormatsBatchProgress {
+                url: url.clone(),
+                success: entry.error.is_none(),
+                completed: results.len() + 1,
+                total,
+            },
+        );
+        results.insert(url, entry);
+    }
+
+    Ok(results)
 }
 
 #[tauri::command]
+pub async fn fetch_audio_formats(
+    app: AppHandle,
+    url: String,
+    extra_args: Option<Vec<String>>,
+    request_id: Option<String>,
+    auth: Option<AuthCredentials>,
+    player_client: Option<PlayerClient>,
+) -> Result<Vec<AudioQualityOption>, CommandError> {
+    let mut extra_args = resolve_extra_args(extra_args)?;
+    if let Some(auth) = auth.as_ref() {
+        extra_args.extend(auth_args(auth));
+    }
+    let (json, _) = fetch_video_json(
+        &app,
+        url,
+        extra_args,
+        request_id.as_deref(),
+        player_client.unwrap_or_default(),
+    )
+    .await?;
+
+    let formats = json["formats"].as_array().ok_or("No formats found")?;
+    let duration = json["duration"].as_f64().unwrap_or(0.0);
+
+    let mut audio_formats: Vec<AudioQualityOption> = formats
+        .iter()
+        .filter(|format| {
+            let vcodec = format["vcodec"].as_str().unwrap_or("none");
+            let acodec = format["acodec"].as_str().unwrap_or("none");
+            (vcodec == "none" || vcodec.is_empty()) && acodec != "none" && !acodec.is_empty()
+        })
+        .filter_map(|format| {
+            let format_id = format["format_id"].as_str()?.to_string();
+            let abr = format["abr"].as_f64().unwrap_or(0.0);
+            let tbr = format["tbr"].as_f64().unwrap_or(0.0);
+            let bitrate = if abr > 0.0 { abr } else { tbr };
+
+            let (size, is_estimated) = match direct_filesize(format) {
+                Some(size) => (size, false),
+                None => (estimate_size(bitrate, duration), true),
+            };
+
+            Some(AudioQualityOption {
+                format_id,
+                codec: format["acodec"].as_str().unwrap_or("unknown").to_string(),
+                ext: format["ext"].as_str().unwrap_or("").to_string(),
+                bitrate,
+                quality: if bitrate > 0.0 {
+                    format!("{:.0}k", bitrate)
+                } else {
+                    "Unknown bitrate".to_string()
+                },
+                size,
+                size_formatted: format_size(size, is_estimated),
+                language: format["language"].as_str().map(|lang| lang.to_string()),
+            })
+        })
+        .collect();
+
+    audio_formats.sort_by(|a, b| b.bitrate.total_cmp(&a.bitrate));
+
+    Ok(audio_formats)
+}
+
+/// How a spawned download ended, for callers that sequence several downloads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DownloadOutcome {
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// What a spawned download's handle resolves to.
+#[derive(Clone, Debug)]
+pub struct DownloadResult {
+    pub outcome: DownloadOutcome,
+    /// Where the finished file ended up, for completed downloads.
+    pub final_path: Option<PathBuf>,
+}
+
+/// `format_string` is either a yt-dlp selector (usually one picked from
+/// `fetch_formats`) or a symbolic quality such as "1080p" or "audio". The
+/// symbolic form skips the `fetch_formats` round-trip and lets yt-dlp choose
+/// with `-S res:N`, so queueing is instant but no size is known upfront.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn start_download(
     app: AppHandle,
     id: String,
@@ -296,66 +1713,231 @@ pub async fn start_download(
     format_string: String,
     subtitles: bool,
     use_aria2c: bool,
-) -> Result<(), String> {
-    let sidecar_command = app.shell().sidecar("yt-dlp").map_err(|e| e.to_string())?;
-
-    let ffmpeg_path = {
-        let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
-        let exe_dir = exe_path.parent().ok_or("Failed to get exe directory")?;
-        let target = tauri::utils::platform::target_triple().map_err(|e| e.to_string())?;
-        let ffmpeg_exe_with_target = format!("ffmpeg-{}.exe", target);
-        let ffmpeg_exe_simple = "ffmpeg.exe";
-
-        let possible_paths = vec![
-            exe_dir.join(ffmpeg_exe_simple),
-            exe_dir.join(&ffmpeg_exe_with_target),
-            exe_dir.join("binaries").join(ffmpeg_exe_simple),
-            exe_dir.join("binaries").join(&ffmpeg_exe_with_target),
-            PathBuf::from("binaries").join(&ffmpeg_exe_with_target),
-            PathBuf::from("src-tauri/binaries").join(&ffmpeg_exe_with_target),
-        ];
-
-        let mut found_path = None;
-        for path in &possible_paths {
-            if path.exists() {
-                found_path = Some(
-                    path.canonicalize()
-                        .unwrap_or_else(|_| path.to_path_buf())
-                        .to_string_lossy()
-                        .to_string(),
-                );
-                break;
-            }
-        }
+    options: Option<DownloadOptions>,
+) -> Result<(), CommandError> {
+    let options = options.unwrap_or_default();
+    check_duplicate(&app, &url, &download_dir, &options)?;
+    validate_format_ids(&app, &url, &options).await?;
+    spawn_download(
+        &app,
+        DownloadJob {
+            id,
+            url,
+            download_dir,
+            format_string,
+            subtitles,
+            use_aria2c,
+            options,
+        },
+    )?;
+    Ok(())
+}
+
+/// Returns the yt-dlp command line `start_download` would run for these
+/// params, quoted for pasting into a terminal. Nothing is spawned and no
+/// folders are created; credentials are redacted.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn preview_command(
+    app: AppHandle,
+    id: String,
+    url: String,
+    download_dir: String,
+    format_string: String,
+    subtitles: bool,
+    use_aria2c: bool,
+    options: Option<DownloadOptions>,
+) -> Result<String, CommandError> {
+    let job = DownloadJob {
+        id,
+        url,
+        download_dir,
+        format_string,
+        subtitles,
+        use_aria2c,
+        options: options.unwrap_or_default(),
+    };
+    let DownloadArgs { args, .. } = build_download_args(&app, &job, &current_settings())?;
+    let mut command = vec!["yt-dlp".to_string()];
+    command.extend(redact_args(&args));
+    Ok(shell_join(&command))
+}
+
+/// Fails with `PossibleDuplicate` when `url` looks already downloaded and
+/// `options.allow_duplicate` isn't set.
+pub fn check_duplicate(
+    app: &AppHandle,
+    url: &str,
+    download_dir: &str,
+    options: &DownloadOptions,
+) -> Result<(), CommandError> {
+    if options.allow_duplicate {
+        return Ok(());
+    }
+    match find_possible_duplicate(app, url, download_dir) {
+        Some(path) => Err(CommandError::PossibleDuplicate {
+            message: format!("This video may already be downloaded at {}", path),
+            path,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// When `options` names exact format ids, checks with a quick simulate run
+/// that `url` really offers them, so a typo fails before anything starts.
+pub async fn validate_format_ids(
+    app: &AppHandle,
+    url: &str,
+    options: &DownloadOptions,
+) -> Result<(), CommandError> {
+    let Some(video_format_id) = explicit_format_id(&options.video_format_id) else {
+        return Ok(());
+    };
+    let format_string = match explicit_format_id(&options.audio_format_id) {
+        Some(audio_format_id) => format!("{}+{}", video_format_id, audio_format_id),
+        None => video_format_id.to_string(),
+    };
+    let (_, height) =
+        resolve_format(app, url, &format_string, None, options.auth.as_ref()).await?;
+    // An explicit pick is still held to the height cap from settings.
+    match (height, current_settings().max_height) {
+        (Some(height), Some(max_height)) if height > max_height => Err(format!(
+            "Format {} is {}p, above the {}p limit in settings",
+            video_format_id, height, max_height
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+fn explicit_format_id(format_id: &Option<String>) -> Option<&str> {
+    format_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|format_id| !format_id.is_empty())
+}
 
-        match found_path {
-            Some(path) => path,
-            None => exe_dir.join(ffmpeg_exe_simple).to_string_lossy().to_string(),
+/// Expands a symbolic quality ("1080p", "audio") into the selector the
+/// height path of `build_download_args` turns into `-S res:N -f bv+ba/b`.
+pub fn symbolic_format_string(quality: &str) -> Option<String> {
+    let quality = quality.trim().to_ascii_lowercase();
+    if quality == "audio" {
+        return Some("ba/b".to_string());
+    }
+    let height = quality.strip_suffix('p')?.parse::<u32>().ok()?;
+    Some(format!("bv*[height<={}]+ba/b[height<={}]/best", height, height))
+}
+
+/// Everything `spawn_download` needs from the arg-building step.
+pub struct DownloadArgs {
+    pub args: Vec<String>,
+    /// The requested dir, or the per-type override from settings.
+    pub download_dir: String,
+    pub download_temp_dir: PathBuf,
+    pub is_audio_only: bool,
+    /// Live stream: progress is reported as recorded duration, not a percentage.
+    pub is_live: bool,
+    /// False when the requested languages turned out to have no tracks.
+    pub subtitles: bool,
+    pub subtitles_skipped: bool,
+    /// Several subtitle languages were requested into an explicit mp4.
+    pub multi_subtitles_in_mp4: bool,
+    pub extractor_skip: &'static str,
+    /// Position of the `--extractor-args` value, swapped on throttle recovery.
+    pub extractor_args_index: usize,
+    /// Where the scheduled `--limit-rate` goes; `None` when the extra args
+    /// set their own.
+    pub rate_limit_index: Option<usize>,
+    pub custom_thumbnail: Option<PathBuf>,
+    pub quality_preference: QualityPreference,
+}
+
+/// Whether `dir` is the OS download folder the frontend starts out with (or
+/// left empty), i.e. not a folder picked for this download.
+fn is_default_download_dir(app: &AppHandle, dir: &str) -> bool {
+    let normalize = |path: &str| {
+        let path = path.trim().trim_end_matches(['/', '\\']).replace('\\', "/");
+        if cfg!(windows) {
+            path.to_lowercase()
+        } else {
+            path
         }
     };
+    let dir = normalize(dir);
+    dir.is_empty()
+        || app
+            .path()
+            .download_dir()
+            .is_ok_and(|default_dir| normalize(&default_dir.to_string_lossy()) == dir)
+}
 
-    let is_audio_only = format_string == "ba/b";
-    let output_template = "%(title)s.%(ext)s".to_string();
-    let home_path = format!("home:{}", download_dir);
-    let download_temp_dir = PathBuf::from(&download_dir).join("_dlpgui_temp").join(&id);
-    if let Err(err) = std::fs::create_dir_all(&download_temp_dir) {
-        println!(
-            "[WARN] Failed to create yt-dlp temp directory {:?}: {}",
-            download_temp_dir, err
-        );
+/// Builds the full yt-dlp arg vector for `job` without touching the disk or
+/// spawning anything, so `preview_command` shows exactly what runs.
+pub fn build_download_args(
+    app: &AppHandle,
+    job: &DownloadJob,
+    settings: &AppSettings,
+) -> Result<DownloadArgs, CommandError> {
+    let DownloadJob {
+        id,
+        url,
+        download_dir,
+        format_string,
+        subtitles,
+        use_aria2c,
+        options,
+    } = job;
+    let is_live = options.live_from_start
+        || cached_json_for_url(url).is_some_and(|json| json["is_live"].as_bool() == Some(true));
+    // aria2c can't follow a growing live manifest.
+    let (subtitles, use_aria2c) = (*subtitles, *use_aria2c && !is_live);
+    let symbolic_format = symbolic_format_string(format_string);
+    let format_string = symbolic_format.as_ref().unwrap_or(format_string);
+    let extra_args = resolve_extra_args(Some(options.extra_args.clone()))?;
+    let ffmpeg_path = resolve_ffmpeg_path()?;
+
+    let audio_format_id = explicit_format_id(&options.audio_format_id);
+    let video_format_id = explicit_format_id(&options.video_format_id);
+    let is_audio_only =
+        format_string == "ba/b" || (audio_format_id.is_some() && video_format_id.is_none());
+    let routed_dir = if is_audio_only {
+        settings.audio_download_dir.as_deref()
+    } else {
+        settings.video_download_dir.as_deref()
+    };
+    // A folder the user picked for this download wins over the routing.
+    let download_dir = routed_dir
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty() && is_default_download_dir(app, download_dir))
+        .map(|dir| dir.to_string())
+        .unwrap_or_else(|| download_dir.clone());
+    let mut output_template = resolve_output_template(settings)?;
+    let sections = chapter_sections(&options.chapters)?;
+    // Every section is its own file; keep them from overwriting each other.
+    if sections.len() > 1 {
+        if let Some(stem) = output_template.strip_suffix(".%(ext)s") {
+            output_template = format!("{} [%(section_start)d-%(section_end)d].%(ext)s", stem);
+        }
     }
+    let home_path = format!("home:{}", download_dir);
+    // yt-dlp moves finished files from temp: to home: with shutil.move, which
+    // already falls back to copy + delete when the temp dir is on another volume.
+    let temp_root = settings
+        .temp_dir
+        .clone()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(&download_dir));
+    let download_temp_dir = temp_root.join("_dlpgui_temp").join(id);
     let temp_path = format!("temp:{}", download_temp_dir.to_string_lossy());
     let subtitle_path = format!("subtitle:{}", download_temp_dir.to_string_lossy());
 
-    let mut args = vec![
+    let mut args = base_ytdlp_args(app);
+    args.extend([
         "--progress".to_string(),
         "--newline".to_string(),
         "--no-update".to_string(),
         "--no-playlist".to_string(),
-        "--js-runtimes".to_string(),
-        "node".to_string(),
-        "--remote-components".to_string(),
-        "ejs:github".to_string(),
         "--ffmpeg-location".to_string(),
         ffmpeg_path,
         "--no-keep-fragments".to_string(),
@@ -363,66 +1945,439 @@ pub async fn start_download(
         home_path,
         "-P".to_string(),
         temp_path,
-        "-P".to_string(),
-        subtitle_path,
         "-o".to_string(),
         output_template,
-    ];
+    ]);
+    // Embedded subtitles are only needed in the temp dir, but a live chat file
+    // has to follow the video into the download folder.
+    if !options.download_live_chat {
+        args.push("-P".to_string());
+        args.push(subtitle_path);
+    }
+
+    if settings.restrict_filenames {
+        args.push("--restrict-filenames".to_string());
+    }
+    if settings.windows_filenames {
+        args.push("--windows-filenames".to_string());
+    }
+    let ip_version = options.ip_version.unwrap_or(settings.ip_version);
+    args.extend(ip_version.as_arg().map(str::to_string));
+    // Explicit either way, since yt-dlp's own default has changed between releases.
+    args.push(
+        match settings.file_timestamp {
+            FileTimestamp::UploadDate => "--mtime",
+            FileTimestamp::DownloadTime => "--no-mtime",
+        }
+        .to_string(),
+    );
+
+    // Players handle several mov_text tracks in an mp4 poorly, so multiple
+    // languages default to mkv; an explicit mp4 is kept with a warning.
+    let multiple_subtitle_languages = subtitles && options.subtitle_languages.len() > 1;
+    let explicit_merge_output_format = options
+        .merge_output_format
+        .as_deref()
+        .map(|format| format.trim().to_ascii_lowercase())
+        .filter(|format| !format.is_empty());
+    let multi_subtitles_in_mp4 = multiple_subtitle_languages
+        && !is_audio_only
+        && explicit_merge_output_format.as_deref() == Some("mp4");
+    let merge_output_format = explicit_merge_output_format.unwrap_or_else(|| {
+        if multiple_subtitle_languages { "mkv" } else { "mp4" }.to_string()
+    });
+    if !SUPPORTED_MERGE_FORMATS.contains(&merge_output_format.as_str()) {
+        return Err(format!(
+            "Unsupported output container '{}'; expected one of {}",
+            merge_output_format,
+            SUPPORTED_MERGE_FORMATS.join(", ")
+        )
+        .into());
+    }
+
+    if options.embed_info_json && (is_audio_only || merge_output_format != "mkv") {
+        return Err("Embedding the info.json is only supported for mkv video downloads; \
+            switch the output container to mkv or disable the option"
+            .into());
+    }
+
+    // An audio download keeps the picked format's container; only an explicit
+    // format id tells which one that is before the download.
+    let thumbnail_container = if is_audio_only {
+        audio_format_id.and_then(|format_id| {
+            cached_json_for_url(url)?["formats"]
+                .as_array()?
+                .iter()
+                .find(|format| format["format_id"].as_str() == Some(format_id))?["ext"]
+                .as_str()
+                .map(str::to_string)
+        })
+    } else {
+        Some(merge_output_format.clone())
+    };
+    let custom_thumbnail = options
+        .custom_thumbnail
+        .as_deref()
+        .filter(|path| !path.trim().is_empty())
+        .map(|path| validate_cover_image(path, thumbnail_container.as_deref()))
+        .transpose()?;
 
     if !is_audio_only {
         args.push("--merge-output-format".to_string());
-        args.push("mp4".to_string());
-        args.push("--embed-thumbnail".to_string());
+        args.push(merge_output_format.clone());
+        if custom_thumbnail.is_none() {
+            // YouTube's best thumbnail is usually webp, which mp4 can't carry;
+            // without converting it the embed is skipped with only a warning.
+            args.push("--embed-thumbnail".to_string());
+            args.push("--convert-thumbnails".to_string());
+            args.push(thumbnail_format_for(&merge_output_format).to_string());
+        }
     }
 
-    let extractor_skip = if subtitles {
+    if options.embed_info_json {
+        args.push("--embed-info-json".to_string());
+    }
+
+    let sub_langs = subtitle_languages(options)?;
+    let subtitles_skipped = subtitles && has_requested_subtitles(url, &sub_langs) == Some(false);
+    let subtitles = subtitles && !subtitles_skipped;
+
+    // Live-from-start needs YouTube's DASH manifest and live HLS needs HLS,
+    // so neither protocol can be skipped.
+    let extractor_skip = if is_live {
+        "translated_subs"
+    } else if subtitles {
         if use_aria2c {
-            "youtube:skip=hls,translated_subs"
+            "hls,translated_subs"
         } else {
-            "youtube:skip=dash,translated_subs"
+            "dash,translated_subs"
         }
     } else if use_aria2c {
-        "youtube:skip=hls"
+        "hls"
     } else {
-        "youtube:skip=dash"
+        "dash"
     };
 
     args.push("--extractor-args".to_string());
-    args.push(extractor_skip.to_string());
+    let extractor_args_index = args.len();
+    args.push(youtube_extractor_args(extractor_skip, options.player_client));
 
     if use_aria2c {
         args.push("--downloader".to_string());
         args.push("aria2c".to_string());
         args.push("--downloader-args".to_string());
-        args.push("aria2c:-x16 -s16 -k1M --file-allocation=none --check-certificate=false".to_string());
+        args.push(aria2c_downloader_args(settings));
+    }
+    let chunk_size = options
+        .http_chunk_size
+        .as_deref()
+        .map(http_chunk_size)
+        .transpose()?
+        .flatten();
+    if let (false, Some(chunk_size)) = (use_aria2c, chunk_size) {
+        args.push("--http-chunk-size".to_string());
+        args.push(chunk_size);
     }
 
-    let height_re = Regex::new(r"height<=(\d+)").unwrap();
-    if let Some(caps) = height_re.captures(&format_string) {
-        let height = &caps[1];
+    let height_re = Regex::new(r"height<=\??(\d+)").unwrap();
+    let remembered_quality = if is_audio_only {
+        Some("audio".to_string())
+    } else if format_string == BEST_QUALITY_FORMAT {
+        Some(BEST_QUALITY_FORMAT.to_string())
+    } else {
+        height_re
+            .captures(format_string)
+            .map(|caps| format!("{}p", &caps[1]))
+    };
+    if let Some(video_format_id) = video_format_id {
+        args.push("-f".to_string());
+        args.push(match audio_format_id {
+            Some(audio_format_id) => format!("{}+{}", video_format_id, audio_format_id),
+            None => video_format_id.to_string(),
+        });
+    } else if let Some(format_id) = audio_format_id {
+        args.push("-f".to_string());
+        args.push(format!("{}/ba/b", format_id));
+    } else if format_string == BEST_QUALITY_FORMAT {
+        args.push("-S".to_string());
+        args.push(match settings.max_height {
+            Some(max_height) => format!("res:{},fps,vcodec", max_height),
+            None => "res,fps,vcodec".to_string(),
+        });
+        args.push("-f".to_string());
+        // `<=?` lets through formats that report no height, such as audio.
+        args.push(match settings.max_height {
+            Some(max_height) => format!("(bv*+ba/b)[height<=?{}]", max_height),
+            None => "bv*+ba/b".to_string(),
+        });
+    } else if let Some(caps) = height_re.captures(format_string) {
+        let requested_height = caps[1].parse::<u32>().unwrap_or(u32::MAX);
+        let height = settings
+            .max_height
+            .map_or(requested_height, |max_height| requested_height.min(max_height));
         args.push("-S".to_string());
         args.push(format!("res:{}", height));
+        let selection = if options.prefer_progressive {
+            format!("b[height<={}]/bv+ba/b", height)
+        } else {
+            "bv+ba/b".to_string()
+        };
         args.push("-f".to_string());
-        args.push("bv+ba/b".to_string());
+        args.push(match settings.max_height {
+            Some(max_height) => format!("({})[height<=?{}]", selection, max_height),
+            None => selection,
+        });
     } else {
         args.push("-f".to_string());
-        args.push(format_string.clone());
+        // Filtering the whole selection keeps exact format ids under the cap too.
+        args.push(match settings.max_height {
+            Some(max_height) if !is_audio_only => {
+                format!("({})[height<=?{}]", format_string, max_height)
+            }
+            _ => format_string.clone(),
+        });
     }
 
-    if subtitles {
+    if subtitles || options.download_live_chat {
         args.push("--write-subs".to_string());
-        args.push("--write-auto-sub".to_string());
-        if !is_audio_only {
-            args.push("--embed-subs".to_string());
+        if subtitles {
+            args.push("--write-auto-sub".to_string());
+            // --embed-subs skips live_chat, so the chat is kept as its own file.
+            if !is_audio_only {
+                args.push("--embed-subs".to_string());
+            }
         }
         args.push("--sub-langs".to_string());
-        args.push("en,en-US,en-GB,en-orig,-live_chat".to_string());
+        args.push(match (subtitles, options.download_live_chat) {
+            (true, true) => sub_langs.replace("-live_chat", "live_chat"),
+            (true, false) => sub_langs,
+            (false, _) => "live_chat".to_string(),
+        });
+    }
+
+    if options.keep_audio_copy && !is_audio_only {
+        // Extract audio from the merged file and keep the video (-k), so both
+        // outputs come from a single download.
+        args.push("--extract-audio".to_string());
+        args.push("--keep-video".to_string());
+        args.push("--audio-format".to_string());
+        args.push(
+            options
+                .audio_copy_format
+                .clone()
+                .unwrap_or_else(|| "best".to_string()),
+        );
+    } else if options.keep_streams && !is_audio_only {
+        args.push("--keep-video".to_string());
+    }
+
+    if options.write_description {
+        args.push("--write-description".to_string());
+    }
+
+    if options.write_comments {
+        // Comments only reach disk inside the info.json sidecar.
+        args.push("--write-comments".to_string());
+        args.push("--write-info-json".to_string());
+    }
+
+    if let Some(retries) = options.fragment_retries {
+        args.push("--fragment-retries".to_string());
+        args.push(retries.to_string());
+    }
+
+    match options.skip_unavailable_fragments {
+        Some(true) => args.push("--skip-unavailable-fragments".to_string()),
+        Some(false) => args.push("--abort-on-unavailable-fragments".to_string()),
+        None => {}
+    }
+
+    if let Some(wait_range) = options.wait_for_video.as_deref() {
+        let wait_re = Regex::new(r"^\d+(-\d+)?$").unwrap();
+        let wait_range = wait_range.trim();
+        if !wait_re.is_match(wait_range) {
+            return Err(format!(
+                "Invalid wait_for_video value '{}', expected MIN or MIN-MAX seconds",
+                wait_range
+            )
+            .into());
+        }
+        args.push("--wait-for-video".to_string());
+        args.push(wait_range.to_string());
+    }
+
+    // Only audio extraction reports ffmpeg progress.
+    let extracts_audio = is_audio_only || options.keep_audio_copy;
+    if extracts_audio {
+        // ffmpeg reads ppa args through shlex, so keep the path free of backslashes.
+        let ffmpeg_progress_path = download_temp_dir
+            .join(FFMPEG_PROGRESS_FILE)
+            .to_string_lossy()
+            .replace('\\', "/");
+        args.push("--postprocessor-args".to_string());
+        args.push(format!(
+            "ExtractAudio:-progress \"{}\" -nostats",
+            ffmpeg_progress_path
+        ));
+    }
+    for section in sections {
+        args.push("--download-sections".to_string());
+        args.push(section);
+    }
+    if options.sponsorblock_chapters {
+        args.push("--sponsorblock-mark".to_string());
+        args.push("all".to_string());
+        args.push("--embed-chapters".to_string());
+    }
+    if let Some(archive) = options.download_archive.as_deref() {
+        args.push("--download-archive".to_string());
+        args.push(archive.to_string());
+    }
+    if let Some(ppa) = options.postprocessor_args.as_deref() {
+        if let Some(ppa) = ffmpeg_postprocessor_args(ppa)? {
+            args.push("--postprocessor-args".to_string());
+            args.push(ppa);
+        }
+    }
+    if extracts_audio {
+        args.push("--print-to-file".to_string());
+        args.push("%(duration)s".to_string());
+        args.push(download_temp_dir.join(DURATION_FILE).to_string_lossy().to_string());
+    }
+    // Remuxing, recoding or an incompatible merge can change the extension, so
+    // take the real path from yt-dlp instead of guessing it from the log.
+    args.push("--print-to-file".to_string());
+    args.push("after_move:%(filepath)s".to_string());
+    args.push(download_temp_dir.join(FINAL_PATH_FILE).to_string_lossy().to_string());
+
+    // Live downloads skip -N: concurrent fragments race ahead of a live edge
+    // that hasn't been written yet.
+    if is_live {
+        if options.live_from_start {
+            args.push("--live-from-start".to_string());
+        }
+        // MPEG-TS stays playable while the stream is still being recorded.
+        args.push("--hls-use-mpegts".to_string());
+    } else {
+        args.push("-N".to_string());
+        args.push("4".to_string());
+    }
+    // A rate from the user's own extra args is left alone.
+    let rate_limit_index = (!sets_rate_limit(&extra_args)).then_some(args.len());
+    if let (Some(_), Some(rate)) = (rate_limit_index, scheduled_rate_limit(settings)) {
+        args.push("--limit-rate".to_string());
+        args.push(rate);
+    }
+    args.extend(extra_args);
+    if let Some(auth) = options.auth.as_ref() {
+        args.extend(auth_args(auth));
+    }
+    args.push(url.to_string());
+
+    Ok(DownloadArgs {
+        args,
+        download_dir,
+        download_temp_dir,
+        is_audio_only,
+        is_live,
+        subtitles,
+        subtitles_skipped,
+        multi_subtitles_in_mp4,
+        extractor_skip,
+        extractor_args_index,
+        rate_limit_index,
+        custom_thumbnail,
+        quality_preference: QualityPreference {
+            quality: remembered_quality,
+            format_string: format_string.to_string(),
+        },
+    })
+}
+
+/// Starts yt-dlp for `job` and returns a handle that resolves once the
+/// process exits and the final `download-status` event has been emitted.
+pub fn spawn_download(
+    app: &AppHandle,
+    job: DownloadJob,
+) -> Result<JoinHandle<DownloadResult>, CommandError> {
+    let settings = current_settings();
+    let DownloadArgs {
+        args,
+        download_dir,
+        download_temp_dir,
+        is_audio_only,
+        is_live,
+        subtitles,
+        subtitles_skipped,
+        multi_subtitles_in_mp4,
+        extractor_skip,
+        extractor_args_index,
+        rate_limit_index,
+        custom_thumbnail,
+        quality_preference,
+    } = build_download_args(app, &job, &settings)?;
+    let running_job = job.clone();
+    let DownloadJob {
+        id,
+        url,
+        format_string,
+        use_aria2c,
+        options,
+        ..
+    } = job;
+    let sidecar_command = ytdlp_command(app).map_err(|e| e.to_string())?;
+    ensure_writable_dir(Path::new(&download_dir), "Download folder")?;
+    ensure_writable_dir(&download_temp_dir, "Temporary folder")?;
+    warn_if_low_disk_space(app, &id, &url, &format_string, Path::new(&download_dir));
+    if subtitles_skipped {
+        let _ = app.emit(
+            "download-log",
+            serde_json::json!({
+                "id": id.clone(),
+                "message": "No subtitles in the requested languages; skipping subtitles.",
+            }),
+        );
+    }
+    if multi_subtitles_in_mp4 {
+        let _ = app.emit(
+            "download-log",
+            serde_json::json!({
+                "id": id.clone(),
+                "message": "Several subtitle tracks in an mp4 may not show up in every player; \
+                    mkv handles them better.",
+            }),
+        );
     }
-
-    args.push("-N".to_string());
-    args.push("4".to_string());
-    args.push(url);
-
+    remember_quality(app, &url, quality_preference);
+    tracing::debug!("yt-dlp args: {:?}", redact_args(&args));
+
+    // Kept so a paused download can be restarted with exactly the same args.
+    let mut resume_args = args.clone();
+    let throttle_client = options.player_client.throttle_fallback();
+    let mut throttle_extractor_args = youtube_extractor_args(extractor_skip, throttle_client);
+    let mut extractor_args_index = extractor_args_index;
+    let mut rate_limit_index = rate_limit_index;
+    // aria2c gets 403s on some DASH videos; the fallback is the same job on
+    // the native downloader, which also skips dash instead of hls.
+    let native_job = DownloadJob {
+        use_aria2c: false,
+        ..running_job.clone()
+    };
+    // A live stream only arrives in real time, which would look like throttling.
+    let mut throttle_detector =
+        ThrottleDetector::from_settings(&settings).filter(|_| !is_live);
+    let mut progress_throttle = ProgressThrottle::default();
+    let downloaded_url = url.clone();
+    let mut resumable_entry = ResumableDownload {
+        id: id.clone(),
+        url,
+        download_dir: download_dir.clone(),
+        format_string: format_string.clone(),
+        subtitles,
+        use_aria2c,
+        temp_dir: download_temp_dir.to_string_lossy().to_string(),
+        cancelled_at: 0,
+    };
     let (mut rx, child) = sidecar_command
         .args(args)
         .spawn()
@@ -432,14 +2387,61 @@ pub async fn start_download(
         let mut downloads = ACTIVE_DOWNLOADS.lock().map_err(|e| e.to_string())?;
         downloads.insert(id.clone(), child);
     }
+    let (finished_tx, finished) = watch::channel(());
+    if let Ok(mut running) = RUNNING_DOWNLOADS.lock() {
+        running.insert(
+            id.clone(),
+            RunningDownload {
+                job: running_job,
+                finished,
+            },
+        );
+    }
+    // Starting the id again picks up any kept partial files, so it is no
+    // longer pending resumption.
+    remove_resumable_download(app, &id);
+
+    if options.write_comments {
+        let _ = app.emit(
+            "download-log",
+            serde_json::json!({
+                "id": id.clone(),
+                "message": "Comment download enabled: extraction can take several minutes for popular videos.",
+            }),
+        );
+    }
 
     let app_clone = app.clone();
     let id_clone = id.clone();
     let temp_dir_for_cleanup = download_temp_dir.clone();
+    let download_dir_path = PathBuf::from(&download_dir);
+    let default_category = if is_audio_only { "audio" } else { "video" };
+    let category = if options.organize_by_category {
+        Some(
+            options
+                .category
+                .clone()
+                .unwrap_or_else(|| default_category.to_string()),
+        )
+    } else {
+        None
+    };
 
-    tokio::spawn(async move {
-        let mut current_phase = "downloading".to_string();
-        let mut download_count = 0;
+    let keep_audio_copy = options.keep_audio_copy && !is_audio_only;
+    let keep_streams = options.keep_streams && !is_audio_only;
+    // Each written track (chat aside) is embedded via --embed-subs.
+    let embeds_subtitles = subtitles && !is_audio_only;
+    let verify_output = options.verify_output;
+    let max_auto_retries = options.auto_retries.unwrap_or(0);
+    // Auth, player client and IP version to list formats with if the
+    // selection turns out not to be available.
+    let format_lookup_options = options.clone();
+
+    Ok(tokio::spawn(async move {
+        let mut outcome = DownloadOutcome::Failed;
+        let mut completed_path: Option<PathBuf> = None;
+        let mut attempt: u32 = 0;
+        let mut use_aria2c = use_aria2c;
 
         let re_progress = Regex::new(
             r"\[download\]\s+(\d+\.?\d*)%\s+of\s+(~?[\d.]+\s*[kKMGT]?i?B)\s+at\s+([\d.]+\s*[kKMGT]?i?B/s)\s+ETA\s+([\d:]+)"
@@ -455,300 +2457,1176 @@ pub async fn start_download(
         .unwrap();
         let re_progress_simple =
             Regex::new(r"\[download\]\s+(\d+\.?\d*)%\s+of\s+(~?[\d.]+\s*[kKMGT]?i?B)").unwrap();
+        // Live downloads report "<size> at <speed> (<elapsed>)" without a percentage,
+        // or ffmpeg's "size=... time=..." when ffmpeg records the HLS stream.
+        let re_live_progress = Regex::new(
+            r"\[download\]\s+(~?[\d.]+\s*[kKMGT]?i?B)\s+at\s+(.+?)\s+\((\d+:\d{2}(?::\d{2})?)\)",
+        )
+        .unwrap();
+        let re_ffmpeg_live =
+            Regex::new(r"size=\s*(\d+)\s*(?:kB|KiB)\s+time=\s*(\d+:\d{2}:\d{2})").unwrap();
         let re_format_info = Regex::new(r"\[info\].*?:\s*Downloading.*?(video|audio)").unwrap();
         let re_merging = Regex::new(r"\[Merger\]|\[ffmpeg\].*Merging").unwrap();
+        let re_merge_target = Regex::new(r#"Merging formats into "(.+)""#).unwrap();
+        let re_extract_destination =
+            Regex::new(r"\[ExtractAudio\]\s+Destination:\s+(.+)").unwrap();
+        let re_thumbnail_file =
+            Regex::new(r"\[info\] Writing video thumbnail .*? to:\s+.+\.(\w+)$").unwrap();
+        let re_thumbnail_convert =
+            Regex::new(r#"\[ThumbnailsConvertor\] Converting thumbnail ".+" to (\w+)"#).unwrap();
+        let re_thumbnail_embed = Regex::new(r"\[EmbedThumbnail\] \w+: Adding thumbnail").unwrap();
         let re_postprocess =
-            Regex::new(r"\[(ExtractAudio|EmbedSubtitle|EmbedThumbnail|Metadata|FixupM3u8|FixupM4a)\]").unwrap();
+            Regex::new(r"\[(ExtractAudio|EmbedSubtitle|EmbedThumbnail|Metadata|ModifyChapters|FixupM3u8|FixupM4a)\]").unwrap();
+        // SponsorBlock is queried before the download starts, so it only
+        // counts segments and doesn't move the progress to "processing".
+        let re_sponsorblock = Regex::new(r"^\[SponsorBlock\]").unwrap();
+        let re_sponsorblock_found =
+            Regex::new(r"\[SponsorBlock\] Found (\d+) segments").unwrap();
         let re_destination = Regex::new(r"\[download\]\s+Destination:\s+(.+)").unwrap();
         let re_already_downloaded = Regex::new(r"has already been downloaded").unwrap();
+        let re_fragment_failure = Regex::new(
+            r"(?i)giving up after \d+ fragment retries|fragment \d+ not found|fragment not found",
+        )
+        .unwrap();
+        // Too many parallel connections make YouTube answer aria2c with 403s.
+        let re_aria2c_forbidden =
+            Regex::new(r"(?i)status=403|403 forbidden|errorCode=22").unwrap();
+        let re_comments = Regex::new(r"(?i)downloading comment|extracting comments").unwrap();
+        let re_info_json =
+            Regex::new(r"\[info\] Writing video metadata as JSON to:\s+(.+)").unwrap();
+        let re_description =
+            Regex::new(r"\[info\] Writing video description to:\s+(.+)").unwrap();
+        let re_live_chat =
+            Regex::new(r"\[info\] Writing video subtitles to:\s+(.+\.live_chat\.json)").unwrap();
+        let re_subtitle_file = Regex::new(r"\[info\] Writing video subtitles to:\s+").unwrap();
+        let re_premiere_starts =
+            Regex::new(r"(?i)(?:live event will begin|premieres) in (.+?)\.?$").unwrap();
+        let re_wait_remaining =
+            Regex::new(r"\[wait\].*?(?:Remaining time until next attempt|Waiting for):?\s*([\d:]+)").unwrap();
+
+        let output_roots = [temp_dir_for_cleanup.as_path(), download_dir_path.as_path()];
+
+        let emit_waiting = |starts_in: &str| {
+            let _ = app_clone.emit(
+                "download-status",
+                serde_json::json!({
+                    "id": id_clone.clone(),
+                    "status": "waiting",
+                    "message": format!("Premiere starts in {}", starts_in),
+                    "starts_in": starts_in,
+                }),
+            );
+        };
+
+        // Progress across runs: a resumed run re-announces the stream it was
+        // on, which must not count as the next stream.
+        let mut current_phase = "downloading".to_string();
+        let mut download_count = 0;
+        let mut download_destinations: Vec<String> = Vec::new();
+
+        loop {
+            // Per-run state.
+            let mut paused = false;
+            let mut retrying = false;
+            let mut throttled = false;
+            let mut switching_method = false;
+            if let Some(detector) = throttle_detector.as_mut() {
+                detector.reset();
+            }
+            let mut final_file_name: Option<String> = None;
+            let mut fragment_failure_detected = false;
+            let mut aria2c_forbidden_detected = false;
+            let mut disk_full_detected = false;
+            let mut info_json_file: Option<String> = None;
+            let mut description_file: Option<String> = None;
+            let mut error_lines: Vec<String> = Vec::new();
+            let mut audio_copy_file: Option<String> = None;
+            let mut stream_files: Vec<String> = Vec::new();
+            let mut live_chat_file: Option<String> = None;
+            let mut subtitle_tracks = 0usize;
+            let mut sponsorblock_segments: Option<usize> = None;
+            let mut thumbnail_format: Option<String> = None;
+            let extract_progress_stop = Arc::new(AtomicBool::new(false));
+
+            while let Some(event) = rx.recv().await {
+                match event {
+                    CommandEvent::Stdout(line) => {
+                        let line_str = String::from_utf8_lossy(&line).trim().to_string();
+                        if line_str.is_empty() {
+                            continue;
+                        }
+                        if use_aria2c && re_aria2c_forbidden.is_match(&line_str) {
+                            aria2c_forbidden_detected = true;
+                        }
+                        if is_disk_full(&line_str.to_ascii_lowercase()) {
+                            disk_full_detected = true;
+                        }
 
-        while let Some(event) = rx.recv().await {
-            match event {
-                CommandEvent::Stdout(line) => {
-                    let line_str = String::from_utf8_lossy(&line).trim().to_string();
-                    if line_str.is_empty() {
-                        continue;
-                    }
+                        let is_progress_line = re_progress.is_match(&line_str)
+                            || re_progress_unknown.is_match(&line_str)
+                            || re_aria2c_progress.is_match(&line_str)
+                            || re_progress_simple.is_match(&line_str)
+                            || (is_live && re_live_progress.is_match(&line_str));
+
+                        if let Some(caps) = re_wait_remaining
+                            .captures(&line_str)
+                            .or_else(|| re_premiere_starts.captures(&line_str))
+                        {
+                            emit_waiting(caps[1].trim());
+                        }
 
-                    let is_progress_line = re_progress.is_match(&line_str)
-                        || re_progress_unknown.is_match(&line_str)
-                        || re_aria2c_progress.is_match(&line_str)
-                        || re_progress_simple.is_match(&line_str);
+                        // The chat replay is fetched like a media file but must not
+                        // count as the video/audio stream or become the final path.
+                        let is_live_chat_line = line_str.contains(".live_chat.json");
 
-                    if re_destination.is_match(&line_str) {
-                        download_count += 1;
-                        current_phase = if download_count == 1 {
-                            "video".to_string()
-                        } else {
-                            "audio".to_string()
+                        if let Some(caps) =
+                            re_destination.captures(&line_str).filter(|_| !is_live_chat_line)
+                        {
+                            if keep_streams {
+                                stream_files.push(caps[1].trim().to_string());
+                            }
+                            let destination = caps[1].trim().to_string();
+                            download_count = match download_destinations
+                                .iter()
+                                .position(|seen| *seen == destination)
+                            {
+                                Some(index) => index + 1,
+                                None => {
+                                    download_destinations.push(destination);
+                                    download_destinations.len()
+                                }
+                            };
+                            current_phase = if download_count == 1 {
+                                "video".to_string()
+                            } else {
+                                "audio".to_string()
+                            };
+                        }
+
+                        if let Some(caps) = re_format_info.captures(&line_str) {
+                            current_phase = caps[1].to_lowercase();
+                        }
+
+                        if re_comments.is_match(&line_str) && current_phase != "fetching comments" {
+                            current_phase = "fetching comments".to_string();
+                            progress_throttle.emit(
+                                &app_clone,
+                                DownloadProgress {
+                                    id: id_clone.clone(),
+                                    percentage: 0.0,
+                                    size: String::new(),
+                                    speed: String::new(),
+                                    eta: String::new(),
+                                    status: "downloading".to_string(),
+                                    phase: current_phase.clone(),
+                                    downloaded_bytes: None,
+                                    total_bytes: None,
+                                    speed_bytes_per_sec: None,
+                                    indeterminate: false,
+                                    live_duration: None,
+                                },
+                            );
+                        }
+
+                        if let Some(caps) = re_info_json.captures(&line_str) {
+                            info_json_file =
+                                Some(output_relative_path(caps[1].trim(), &output_roots));
+                        }
+
+                        if let Some(caps) = re_description.captures(&line_str) {
+                            description_file =
+                                Some(output_relative_path(caps[1].trim(), &output_roots));
+                        }
+
+                        if let Some(caps) = re_thumbnail_file
+                            .captures(&line_str)
+                            .or_else(|| re_thumbnail_convert.captures(&line_str))
+                        {
+                            thumbnail_format = Some(caps[1].to_ascii_lowercase());
+                        }
+                        if re_thumbnail_embed.is_match(&line_str) {
+                            let _ = app_clone.emit(
+                                "download-log",
+                                serde_json::json!({
+                                    "id": id_clone.clone(),
+                                    "message": match thumbnail_format.as_deref() {
+                                        Some(format) => format!("Embedding a {} thumbnail", format),
+                                        None => "Embedding the thumbnail".to_string(),
+                                    },
+                                }),
+                            );
+                        }
+
+                        if re_subtitle_file.is_match(&line_str) && !is_live_chat_line {
+                            subtitle_tracks += 1;
+                        }
+
+                        if let Some(caps) = re_sponsorblock_found.captures(&line_str) {
+                            sponsorblock_segments = caps[1].parse().ok();
+                        } else if line_str.contains("No matching segments were found") {
+                            sponsorblock_segments = Some(0);
+                        }
+
+                        if let Some(caps) = re_live_chat.captures(&line_str) {
+                            current_phase = "live chat".to_string();
+                            live_chat_file =
+                                Some(output_relative_path(caps[1].trim(), &output_roots));
+                        }
+
+                        if let Some(caps) = re_extract_destination.captures(&line_str) {
+                            let _ = std::fs::remove_file(
+                                temp_dir_for_cleanup.join(FFMPEG_PROGRESS_FILE),
+                            );
+                            spawn_extract_audio_progress(
+                                app_clone.clone(),
+                                id_clone.clone(),
+                                temp_dir_for_cleanup.clone(),
+                                extract_progress_stop.clone(),
+                            );
+                            let file_name = output_relative_path(caps[1].trim(), &output_roots);
+                            if keep_audio_copy {
+                                audio_copy_file = Some(file_name);
+                            } else {
+                                final_file_name = Some(file_name);
+                            }
+                        } else if let Some(caps) = re_merge_target
+                            .captures(&line_str)
+                            .or_else(|| re_destination.captures(&line_str))
+                            .filter(|_| !is_live_chat_line)
+                        {
+                            final_file_name =
+                                Some(output_relative_path(caps[1].trim(), &output_roots));
+                        }
+
+                        if re_merging.is_match(&line_str) {
+                            current_phase = "merging".to_string();
+                            progress_throttle.emit(
+                                &app_clone,
+                                DownloadProgress {
+                                    id: id_clone.clone(),
+                                    percentage: 99.0,
+                                    size: String::new(),
+                                    speed: String::new(),
+                                    eta: String::new(),
+                                    status: "downloading".to_string(),
+                                    phase: "merging".to_string(),
+                                    downloaded_bytes: None,
+                                    total_bytes: None,
+                                    speed_bytes_per_sec: None,
+                                    indeterminate: false,
+                                    live_duration: None,
+                                },
+                            );
+                        }
+
+                        if re_postprocess.is_match(&line_str) {
+                            current_phase = "processing".to_string();
+                            progress_throttle.emit(
+                                &app_clone,
+                                DownloadProgress {
+                                    id: id_clone.clone(),
+                                    percentage: 99.5,
+                                    size: String::new(),
+                                    speed: String::new(),
+                                    eta: String::new(),
+                                    status: "downloading".to_string(),
+                                    phase: "processing".to_string(),
+                                    downloaded_bytes: None,
+                                    total_bytes: None,
+                                    speed_bytes_per_sec: None,
+                                    indeterminate: false,
+                                    live_duration: None,
+                                },
+                            );
+                        }
+
+                        let adjusted_percent = |raw_percent: f32| -> f32 {
+                            if download_count > 1 {
+                                50.0 + (raw_percent * 0.45)
+                            } else if download_count == 1 {
+                                raw_percent * 0.5
+                            } else {
+                                raw_percent
+                            }
                         };
-                    }
 
-                    if let Some(caps) = re_format_info.captures(&line_str) {
-                        current_phase = caps[1].to_lowercase();
-                    }
+                        if let Some(caps) = re_live_progress
+                            .captures(&line_str)
+                            .filter(|_| is_live)
+                        {
+                            let progress = live_progress_event(
+                                &id_clone,
+                                caps[1].trim(),
+                                caps[2].trim(),
+                                &caps[3],
+                                &current_phase,
+                            );
+                            record_download_stats(&progress);
+                            progress_throttle.emit(&app_clone, progress);
+                        } else if let Some(caps) = re_progress.captures(&line_str) {
+                            let raw_percent = caps[1].parse::<f32>().unwrap_or(0.0);
+                            let progress = progress_event(
+                                &id_clone,
+                                raw_percent,
+                                adjusted_percent(raw_percent),
+                                caps[2].trim(),
+                                caps[3].trim(),
+                                caps[4].trim(),
+                                &current_phase,
+                            );
+                            record_download_stats(&progress);
+                            progress_throttle.emit(&app_clone, progress);
+                        } else if let Some(caps) = re_progress_unknown.captures(&line_str) {
+                            let raw_percent = caps[1].parse::<f32>().unwrap_or(0.0);
+                            let progress = progress_event(
+                                &id_clone,
+                                raw_percent,
+                                adjusted_percent(raw_percent),
+                                caps[2].trim(),
+                                caps[3].trim(),
+                                caps[4].trim(),
+                                &current_phase,
+                            );
+                            record_download_stats(&progress);
+                            progress_throttle.emit(&app_clone, progress);
+                        } else if let Some(caps) = re_aria2c_progress.captures(&line_str) {
+                            let raw_percent = caps[2].parse::<f32>().unwrap_or(0.0);
+                            let progress = progress_event(
+                                &id_clone,
+                                raw_percent,
+                                adjusted_percent(raw_percent),
+                                &caps[1],
+                                &caps[3],
+                                &caps[4],
+                                &current_phase,
+                            );
+                            record_download_stats(&progress);
+                            progress_throttle.emit(&app_clone, progress);
+                        } else if let Some(caps) = re_progress_simple.captures(&line_str) {
+                            let raw_percent = caps[1].parse::<f32>().unwrap_or(0.0);
+                            let progress = progress_event(
+                                &id_clone,
+                                raw_percent,
+                                adjusted_percent(raw_percent),
+                                caps[2].trim(),
+                                "...",
+                                "...",
+                                &current_phase,
+                            );
+                            record_download_stats(&progress);
+                            progress_throttle.emit(&app_clone, progress);
+                        } else if let Some(caps) = re_destination
+                            .captures(&line_str)
+                            .filter(|_| !is_live_chat_line)
+                        {
+                            let filename = file_name_from_path(caps[1].trim());
+                            let _ = app_clone.emit(
+                                "download-title",
+                                serde_json::json!({
+                                    "id": id_clone.clone(),
+                                    "title": filename,
+                                }),
+                            );
+                        } else if re_already_downloaded.is_match(&line_str) {
+                            if let Some(start) = line_str.find("[download] ") {
+                                let rest = &line_str[start + 11..];
+                                if let Some(end) = rest.find(" has already") {
+                                    let filename = file_name_from_path(&rest[..end]);
+                                    final_file_name =
+                                        Some(output_relative_path(&rest[..end], &output_roots));
+                                    let _ = app_clone.emit(
+                                        "download-title",
+                                        serde_json::json!({
+                                            "id": id_clone.clone(),
+                                            "title": filename,
+                                        }),
+                                    );
+                                }
+                            }
+                        }
 
-                    if re_merging.is_match(&line_str) {
-                        current_phase = "merging".to_string();
-                        let _ = app_clone.emit(
-                            "download-progress",
-                            DownloadProgress {
-                                id: id_clone.clone(),
-                                percentage: 99.0,
-                                size: String::new(),
-                                speed: String::new(),
-                                eta: String::new(),
-                                status: "downloading".to_string(),
-                                phase: "merging".to_string(),
-                            },
-                        );
+                        let is_media_phase =
+                            matches!(current_phase.as_str(), "downloading" | "video" | "audio");
+                        let speed = DOWNLOAD_STATS
+                            .lock()
+                            .ok()
+                            .and_then(|stats| stats.get(&id_clone).map(|e| e.speed_bytes_per_sec));
+                        if let (true, true, Some(detector), Some(speed)) =
+                            (is_progress_line, is_media_phase, throttle_detector.as_mut(), speed)
+                        {
+                            if detector.observe(speed) {
+                                // Recover once; a second slow run is left alone.
+                                throttle_detector = None;
+                                throttled = true;
+                                let child = ACTIVE_DOWNLOADS
+                                    .lock()
+                                    .ok()
+                                    .and_then(|mut downloads| downloads.remove(&id_clone));
+                                if let Some(child) = child {
+                                    kill_process_tree(child);
+                                }
+                            }
+                        }
+
+                        let lower_line = line_str.to_ascii_lowercase();
+                        let should_emit_log = !is_progress_line
+                            && !lower_line.contains("no subtitles")
+                            && (re_destination.is_match(&line_str)
+                                || re_merging.is_match(&line_str)
+                                || re_postprocess.is_match(&line_str)
+                                || re_sponsorblock.is_match(&line_str)
+                                || re_already_downloaded.is_match(&line_str)
+                                || lower_line.contains("error")
+                                || lower_line.contains("warning")
+                                || lower_line.contains("failed"));
+
+                        if should_emit_log {
+                            let _ = app_clone.emit(
+                                "download-log",
+                                serde_json::json!({
+                                    "id": id_clone.clone(),
+                                    "message": line_str,
+                                }),
+                            );
+                        }
                     }
+                    CommandEvent::Stderr(line) => {
+                        let line_str = String::from_utf8_lossy(&line).trim().to_string();
+                        if line_str.is_empty() {
+                            continue;
+                        }
 
-                    if re_postprocess.is_match(&line_str) {
-                        current_phase = "processing".to_string();
-                        let _ = app_clone.emit(
-                            "download-progress",
-                            DownloadProgress {
-                                id: id_clone.clone(),
-                                percentage: 99.5,
-                                size: String::new(),
-                                speed: String::new(),
-                                eta: String::new(),
-                                status: "downloading".to_string(),
-                                phase: "processing".to_string(),
-                            },
-                        );
+                        let is_progress_line = re_progress.is_match(&line_str)
+                            || re_progress_unknown.is_match(&line_str)
+                            || re_aria2c_progress.is_match(&line_str)
+                            || re_progress_simple.is_match(&line_str)
+                            || re_ffmpeg_live.is_match(&line_str);
+                        if let Some(caps) = re_ffmpeg_live.captures(&line_str).filter(|_| is_live) {
+                            let progress = live_progress_event(
+                                &id_clone,
+                                &format!("{}KiB", &caps[1]),
+                                "",
+                                &caps[2],
+                                &current_phase,
+                            );
+                            record_download_stats(&progress);
+                            progress_throttle.emit(&app_clone, progress);
+                        }
+                        if let Some(caps) = re_premiere_starts.captures(&line_str) {
+                            emit_waiting(caps[1].trim());
+                        }
+
+                        if re_fragment_failure.is_match(&line_str) {
+                            fragment_failure_detected = true;
+                        }
+                        if use_aria2c && re_aria2c_forbidden.is_match(&line_str) {
+                            aria2c_forbidden_detected = true;
+                        }
+                        // ffmpeg reports a full disk without yt-dlp's "ERROR:" prefix.
+                        let disk_full_line = is_disk_full(&line_str.to_ascii_lowercase());
+                        disk_full_detected |= disk_full_line;
+
+                        // The missing-runtime warning explains the vaguer ERROR that
+                        // follows, so keep it for classification.
+                        if line_str.starts_with("ERROR:")
+                            || disk_full_line
+                            || line_str
+                                .to_ascii_lowercase()
+                                .contains("supported javascript runtime")
+                        {
+                            error_lines.push(line_str.clone());
+                        }
+
+                        let lower_line = line_str.to_ascii_lowercase();
+                        // Missing subtitles are expected for most videos, not worth a log line.
+                        let should_emit_log = !lower_line.contains("no subtitles")
+                            && (!is_progress_line
+                                || lower_line.contains("error")
+                                || lower_line.contains("warning")
+                                || lower_line.contains("failed"));
+
+                        if should_emit_log {
+                            let _ = app_clone.emit(
+                                "download-log",
+                                serde_json::json!({
+                                    "id": id_clone.clone(),
+                                    "message": line_str,
+                                    "is_error": true,
+                                }),
+                            );
+                        }
                     }
+                    CommandEvent::Terminated(payload) => {
+                        progress_throttle.flush(&app_clone);
+                        if throttled {
+                            break;
+                        }
+                        let succeeded = payload.code == Some(0);
+                        let was_paused = !succeeded
+                            && PAUSED_DOWNLOADS
+                                .lock()
+                                .map(|paused_downloads| paused_downloads.contains_key(&id_clone))
+                                .unwrap_or(false);
+                        if was_paused {
+                            paused = true;
+                            break;
+                        }
+                        // cancel_download removes the child before killing it and
+                        // reports the cancellation itself.
+                        let was_cancelled = !succeeded
+                            && ACTIVE_DOWNLOADS
+                                .lock()
+                                .map(|downloads| !downloads.contains_key(&id_clone))
+                                .unwrap_or(false);
+                        if was_cancelled {
+                            outcome = DownloadOutcome::Cancelled;
+                            break;
+                        }
+                        if !succeeded && use_aria2c && aria2c_forbidden_detected {
+                            switching_method = true;
+                            break;
+                        }
+                        if succeeded {
+                            outcome = DownloadOutcome::Completed;
+                        }
+                        let status = if succeeded { "completed" } else { "error" };
+                        // With a kept audio copy the last moved file is the audio.
+                        if succeeded && !keep_audio_copy {
+                            if let Some(path) = last_printed_path(&temp_dir_for_cleanup) {
+                                final_file_name = Some(output_relative_path(&path, &output_roots));
+                            }
+                        }
+                        let mut final_path = final_file_name
+                            .as_ref()
+                            .map(|name| download_dir_path.join(name));
+
+                        if let (true, Some(category), Some(path)) =
+                            (succeeded, category.as_deref(), final_path.clone())
+                        {
+                            match move_into_category_folder(&download_dir_path, &path, category) {
+                                Ok(Some(moved_path)) => final_path = Some(moved_path),
+                                Ok(None) => {}
+                                Err(err) => {
+                                    let _ = app_clone.emit(
+                                        "download-log",
+                                        serde_json::json!({
+                                            "id": id_clone.clone(),
+                                            "message": err,
+                                            "is_error": true,
+                                        }),
+                                    );
+                                }
+                            }
+                        }
 
-                    let adjusted_percent = |raw_percent: f32| -> f32 {
-                        if download_count > 1 {
-                            50.0 + (raw_percent * 0.45)
-                        } else if download_count == 1 {
-                            raw_percent * 0.5
-                        } else {
-                            raw_percent
+                        if let (true, true, Some(path)) =
+                            (succeeded, verify_output, final_path.clone())
+                        {
+                            match verify_output_container(&app_clone, &path).await {
+                                Ok(Some(renamed_path)) => {
+                                    let _ = app_clone.emit(
+                                        "download-log",
+                                        serde_json::json!({
+                                            "id": id_clone.clone(),
+                                            "message": format!(
+                                                "Renamed {:?} to match its container",
+                                                renamed_path
+                                            ),
+                                        }),
+                                    );
+                                    final_path = Some(renamed_path);
+                                }
+                                Ok(None) => {}
+                                Err(err) => {
+                                    let _ = app_clone.emit(
+                                        "download-log",
+                                        serde_json::json!({
+                                            "id": id_clone.clone(),
+                                            "message": err,
+                                            "is_error": true,
+                                        }),
+                                    );
+                                }
+                            }
                         }
-                    };
 
-                    if let Some(caps) = re_progress.captures(&line_str) {
-                        let _ = app_clone.emit(
-                            "download-progress",
-                            DownloadProgress {
-                                id: id_clone.clone(),
-                                percentage: adjusted_percent(
-                                    caps[1].parse::<f32>().unwrap_or(0.0),
-                                ),
-                                size: caps[2].trim().to_string(),
-                                speed: caps[3].trim().to_string(),
-                                eta: caps[4].trim().to_string(),
-                                status: "downloading".to_string(),
-                                phase: current_phase.clone(),
-                            },
-                        );
-                    } else if let Some(caps) = re_progress_unknown.captures(&line_str) {
-                        let _ = app_clone.emit(
-                            "download-progress",
-                            DownloadProgress {
-                                id: id_clone.clone(),
-                                percentage: adjusted_percent(
-                                    caps[1].parse::<f32>().unwrap_or(0.0),
-                                ),
-                                size: caps[2].trim().to_string(),
-                                speed: caps[3].trim().to_string(),
-                                eta: caps[4].trim().to_string(),
-                                status: "downloading".to_string(),
-                                phase: current_phase.clone(),
-                            },
-                        );
-                    } else if let Some(caps) = re_aria2c_progress.captures(&line_str) {
-                        let _ = app_clone.emit(
-                            "download-progress",
-                            DownloadProgress {
-                                id: id_clone.clone(),
-                                percentage: adjusted_percent(
-                                    caps[2].parse::<f32>().unwrap_or(0.0),
-                                ),
-                                size: caps[1].to_string(),
-                                speed: caps[3].to_string(),
-                                eta: caps[4].to_string(),
-                                status: "downloading".to_string(),
-                                phase: current_phase.clone(),
-                            },
-                        );
-                    } else if let Some(caps) = re_progress_simple.captures(&line_str) {
-                        let _ = app_clone.emit(
-                            "download-progress",
-                            DownloadProgress {
-                                id: id_clone.clone(),
-                                percentage: adjusted_percent(
-                                    caps[1].parse::<f32>().unwrap_or(0.0),
-                                ),
-                                size: caps[2].trim().to_string(),
-                                speed: "...".to_string(),
-                                eta: "...".to_string(),
-                                status: "downloading".to_string(),
-                                phase: current_phase.clone(),
-                            },
-                        );
-                    } else if let Some(caps) = re_destination.captures(&line_str) {
-                        let full_path = caps[1].trim();
-                        let filename = full_path
-                            .split(|c| c == '/' || c == '\\')
-                            .last()
-                            .unwrap_or(full_path);
-                        let _ = app_clone.emit(
-                            "download-title",
-                            serde_json::json!({
-                                "id": id_clone.clone(),
-                                "title": filename,
-                            }),
-                        );
-                    } else if re_already_downloaded.is_match(&line_str) {
-                        if let Some(start) = line_str.find("[download] ") {
-                            let rest = &line_str[start + 11..];
-                            if let Some(end) = rest.find(" has already") {
-                                let full_path = &rest[..end];
-                                let filename = full_path
-                                    .split(|c| c == '/' || c == '\\')
-                                    .last()
-                                    .unwrap_or(full_path);
+                        if let (true, Some(image), Some(path)) =
+                            (succeeded, custom_thumbnail.as_ref(), final_path.as_ref())
+                        {
+                            if let Err(err) = embed_cover_art(&app_clone, path, image).await {
                                 let _ = app_clone.emit(
-                                    "download-title",
+                                    "download-log",
                                     serde_json::json!({
                                         "id": id_clone.clone(),
-                                        "title": filename,
+                                        "message": err,
+                                        "is_error": true,
                                     }),
                                 );
                             }
                         }
-                    }
 
-                    let lower_line = line_str.to_ascii_lowercase();
-                    let should_emit_log = !is_progress_line
-                        && (re_destination.is_match(&line_str)
-                            || re_merging.is_match(&line_str)
-                            || re_postprocess.is_match(&line_str)
-                            || re_already_downloaded.is_match(&line_str)
-                            || lower_line.contains("error")
-                            || lower_line.contains("warning")
-                            || lower_line.contains("failed"));
-
-                    if should_emit_log {
+                        // A single combined format has no separate streams to keep.
+                        let stream_paths = if succeeded && stream_files.len() > 1 {
+                            keep_stream_files(
+                                &stream_files,
+                                &temp_dir_for_cleanup,
+                                &download_dir_path,
+                            )
+                        } else {
+                            Vec::new()
+                        };
+
+                        if let (true, Some(path)) = (succeeded, final_path.as_ref()) {
+                            record_downloaded_video(&app_clone, &downloaded_url, path);
+                            completed_path = Some(path.clone());
+                        }
+
+                        let error = (!succeeded).then(|| {
+                            match classify_ytdlp_error(&error_lines.join("\n"), "Download failed") {
+                                error
+                                    if disk_full_detected
+                                        || matches!(error, CommandError::DiskFull { .. }) =>
+                                {
+                                    let full_dir = disk_full_dir(
+                                        &error_lines.join("\n"),
+                                        &temp_dir_for_cleanup,
+                                        &download_dir_path,
+                                    );
+                                    CommandError::DiskFull {
+                                        message: error.message().to_string(),
+                                        path: Some(full_dir.to_string_lossy().to_string()),
+                                    }
+                                }
+                                error => error,
+                            }
+                        });
+
+                        if let Some(error) = error
+                            .as_ref()
+                            .filter(|error| error.is_retryable() && attempt < max_auto_retries)
+                        {
+                            retrying = true;
+                            let _ = app_clone.emit(
+                                "download-status",
+                                serde_json::json!({
+                                    "id": id_clone.clone(),
+                                    "status": "retrying",
+                                    "error": error,
+                                    "attempt": attempt + 1,
+                                    "max_attempts": max_auto_retries + 1,
+                                    "retry_in_secs": (AUTO_RETRY_DELAY * (attempt + 1)).as_secs(),
+                                }),
+                            );
+                            break;
+                        }
+                        // Failing after automatic retries gets its own status so the
+                        // UI can tell "try again" apart from "already tried hard".
+                        let status = if matches!(error, Some(CommandError::DiskFull { .. })) {
+                            "disk_full"
+                        } else if !succeeded && attempt > 0 {
+                            "failed_after_retries"
+                        } else {
+                            status
+                        };
+
+                        let retryable = error.as_ref().is_some_and(CommandError::is_retryable);
+                        let retry_suggestion = if aria2c_forbidden_detected && error.is_some() {
+                            Some("lower_aria2c_connections")
+                        } else if fragment_failure_detected && retryable {
+                            Some("skip_unavailable_fragments")
+                        } else {
+                            None
+                        };
+
                         let _ = app_clone.emit(
-                            "download-log",
+                            "download-status",
                             serde_json::json!({
                                 "id": id_clone.clone(),
-                                "message": line_str,
+                                "status": status,
+                                "error": error,
+                                "attempts": attempt + 1,
+                                "final_extension": final_path
+                                    .as_ref()
+                                    .and_then(|path| path.extension())
+                                    .map(|ext| ext.to_string_lossy().to_ascii_lowercase()),
+                                "final_path": final_path.map(|path| path.to_string_lossy().to_string()),
+                                "retry_suggestion": retry_suggestion,
+                                "audio_path": audio_copy_file
+                                    .as_deref()
+                                    .map(|name| download_path_string(&download_dir_path, name)),
+                                "info_json_path": info_json_file
+                                    .as_deref()
+                                    .map(|name| download_path_string(&download_dir_path, name)),
+                                "description_path": description_file
+                                    .as_deref()
+                                    .map(|name| download_path_string(&download_dir_path, name)),
+                                "live_chat_path": live_chat_file
+                                    .as_deref()
+                                    .map(|name| download_path_string(&download_dir_path, name)),
+                                "subtitle_tracks": embeds_subtitles.then_some(subtitle_tracks),
+                                "stream_paths": keep_streams.then_some(stream_paths),
+                                // None when off or when SponsorBlock was unreachable.
+                                "sponsorblock_segments": sponsorblock_segments,
                             }),
                         );
+                        if matches!(error, Some(CommandError::FormatUnavailable { .. })) {
+                            tokio::spawn(emit_current_formats(
+                                app_clone.clone(),
+                                id_clone.clone(),
+                                downloaded_url.clone(),
+                                format_lookup_options.clone(),
+                            ));
+                        }
+                        break;
                     }
+                    _ => {}
                 }
-                CommandEvent::Stderr(line) => {
-                    let line_str = String::from_utf8_lossy(&line).trim().to_string();
-                    if line_str.is_empty() {
-                        continue;
-                    }
+            }
 
-                    let is_progress_line = re_progress.is_match(&line_str)
-                        || re_progress_unknown.is_match(&line_str)
-                        || re_aria2c_progress.is_match(&line_str)
-                        || re_progress_simple.is_match(&line_str);
-                    let lower_line = line_str.to_ascii_lowercase();
-                    let should_emit_log = !is_progress_line
-                        || lower_line.contains("error")
-                        || lower_line.contains("warning")
-                        || lower_line.contains("failed");
-
-                    if should_emit_log {
+            extract_progress_stop.store(true, Ordering::Relaxed);
+            if throttled {
+                resume_args[extractor_args_index] = throttle_extractor_args.clone();
+                let _ = app_clone.emit(
+                    "download-status",
+                    serde_json::json!({
+                        "id": id_clone.clone(),
+                        "status": "throttled",
+                        "message": format!(
+                            "throttled, retrying with the {} player client",
+                            throttle_client.as_arg().unwrap_or("default")
+                        ),
+                    }),
+                );
+            } else if switching_method {
+                // Only once: with aria2c off the 403 check no longer fires.
+                use_aria2c = false;
+                resumable_entry.use_aria2c = false;
+                match build_download_args(&app_clone, &native_job, &current_settings()) {
+                    Ok(native) => {
+                        resume_args = native.args;
+                        extractor_args_index = native.extractor_args_index;
+                        rate_limit_index = native.rate_limit_index;
+                        throttle_extractor_args =
+                            youtube_extractor_args(native.extractor_skip, throttle_client);
+                    }
+                    Err(error) => {
                         let _ = app_clone.emit(
-                            "download-log",
+                            "download-status",
                             serde_json::json!({
                                 "id": id_clone.clone(),
-                                "message": line_str,
-                                "is_error": true,
+                                "status": "error",
+                                "error": error,
                             }),
                         );
+                        break;
                     }
                 }
-                CommandEvent::Terminated(payload) => {
-                    let status = if payload.code == Some(0) {
-                        "completed"
-                    } else {
-                        "error"
-                    };
+                let _ = app_clone.emit(
+                    "download-status",
+                    serde_json::json!({
+                        "id": id_clone.clone(),
+                        "status": "switching_method",
+                        "message": "aria2c was refused with 403, retrying with the native downloader",
+                    }),
+                );
+            } else if retrying {
+                attempt += 1;
+                tokio::time::sleep(AUTO_RETRY_DELAY * attempt).await;
+                // cancel_download drops the finished child from ACTIVE_DOWNLOADS.
+                let still_active = ACTIVE_DOWNLOADS
+                    .lock()
+                    .map(|downloads| downloads.contains_key(&id_clone))
+                    .unwrap_or(false);
+                if !still_active {
+                    outcome = DownloadOutcome::Cancelled;
+                    break;
+                }
+            } else if paused {
+                let (resume_tx, resume_rx) = oneshot::channel();
+                if let Ok(mut paused_downloads) = PAUSED_DOWNLOADS.lock() {
+                    paused_downloads.insert(id_clone.clone(), Some(resume_tx));
+                }
+                let _ = app_clone.emit(
+                    "download-status",
+                    serde_json::json!({
+                        "id": id_clone.clone(),
+                        "status": "paused",
+                    }),
+                );
+
+                // `false` (or a dropped sender) means the paused download was cancelled.
+                if resume_rx.await != Ok(true) {
+                    outcome = DownloadOutcome::Cancelled;
+                    break;
+                }
+            } else {
+                break;
+            }
+
+            // The schedule may have moved on to another window since the last run.
+            if let Some(index) = rate_limit_index {
+                set_rate_limit(&mut resume_args, index, scheduled_rate_limit(&current_settings()));
+            }
+            let respawned = ytdlp_command(&app_clone)
+                .and_then(|command| command.args(resume_args.clone()).spawn());
+            match respawned {
+                Ok((resumed_rx, child)) => {
+                    if let Ok(mut downloads) = ACTIVE_DOWNLOADS.lock() {
+                        downloads.insert(id_clone.clone(), child);
+                    }
+                    rx = resumed_rx;
+                    let _ = app_clone.emit(
+                        "download-status",
+                        serde_json::json!({
+                            "id": id_clone.clone(),
+                            "status": "downloading",
+                        }),
+                    );
+                }
+                Err(err) => {
+                    let error = CommandError::from(format!("Failed to resume download: {}", err));
                     let _ = app_clone.emit(
                         "download-status",
                         serde_json::json!({
                             "id": id_clone.clone(),
-                            "status": status,
+                            "status": "error",
+                            "error": error,
                         }),
                     );
                     break;
                 }
-                _ => {}
             }
         }
 
         if let Ok(mut downloads) = ACTIVE_DOWNLOADS.lock() {
             downloads.remove(&id_clone);
         }
-
-        if temp_dir_for_cleanup.exists() {
+        if let Ok(mut paused_downloads) = PAUSED_DOWNLOADS.lock() {
+            paused_downloads.remove(&id_clone);
+        }
+        clear_download_stats(&id_clone);
+
+        let keep_partial = KEEP_PARTIAL_DOWNLOADS
+            .lock()
+            .map(|mut keep| keep.remove(&id_clone))
+            .unwrap_or(false);
+        if keep_partial && outcome == DownloadOutcome::Cancelled {
+            let mut entry = resumable_entry;
+            entry.cancelled_at = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0);
+            record_resumable_download(&app_clone, entry);
+        } else if temp_dir_for_cleanup.exists() {
             let _ = std::fs::remove_dir_all(&temp_dir_for_cleanup);
         }
-    });
+        if let Ok(mut running) = RUNNING_DOWNLOADS.lock() {
+            running.remove(&id_clone);
+        }
+        drop(finished_tx);
 
-    Ok(())
+        DownloadResult {
+            outcome,
+            final_path: completed_path,
+        }
+    }))
 }
 
 #[tauri::command]
 pub async fn cancel_download(
     app: AppHandle,
     id: String,
+    keep_partial: Option<bool>,
 ) -> Result<(), String> {
+    let keep_partial = keep_partial.unwrap_or(false);
+    if remove_queued(&id) {
+        let _ = app.emit(
+            "download-status",
+            serde_json::json!({
+                "id": id,
+                "status": "cancelled",
+            }),
+        );
+        return Ok(());
+    }
+
+    // Cancelling a playlist id stops the coordinator and the video it is on;
+    // cancelling a video id only skips that video.
+    let playlist_video = ACTIVE_PLAYLISTS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&id);
+    if let Some(Some(video_id)) = playlist_video {
+        kill_download(&app, &video_id, keep_partial)?;
+    }
+
+    kill_download(&app, &id, keep_partial)
+}
+
+pub fn kill_download(app: &AppHandle, id: &str, keep_partial: bool) -> Result<(), String> {
+    stop_download(id, keep_partial)?;
+    let _ = app.emit(
+        "download-status",
+        serde_json::json!({
+            "id": id,
+            "status": "cancelled",
+        }),
+    );
+    Ok(())
+}
+
+/// Kills `id`'s process (or wakes it from a pause) so its task exits as
+/// cancelled, without reporting anything itself.
+fn stop_download(id: &str, keep_partial: bool) -> Result<(), String> {
+    // A stale entry would keep the partial files of a later download with this id.
+    let known = ACTIVE_DOWNLOADS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .contains_key(id)
+        || PAUSED_DOWNLOADS
+            .lock()
+            .map_err(|e| e.to_string())?
+            .contains_key(id);
+    if keep_partial && known {
+        KEEP_PARTIAL_DOWNLOADS
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(id.to_string());
+    }
+
+    let paused = PAUSED_DOWNLOADS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(id);
+    if let Some(Some(resume_tx)) = paused {
+        let _ = resume_tx.send(false);
+    }
+
     let child_opt = {
         let mut downloads = ACTIVE_DOWNLOADS.lock().map_err(|e| e.to_string())?;
-        downloads.remove(&id)
+        downloads.remove(id)
     };
+    clear_download_stats(id);
 
     if let Some(child) = child_opt {
-        let pid = child.pid();
-
-        #[cfg(target_os = "windows")]
-        {
-            let output = std::process::Command::new("taskkill")
-                .args(["/F", "/T", "/PID", &pid.to_string()])
-                .output();
-
-            match output {
-                Ok(result) => {
-                    if !result.status.success() {
-                        let _ = child.kill();
-                    }
-                }
-                Err(_) => {
-                    let _ = child.kill();
-                }
-            }
-        }
+        kill_process_tree(child);
+    }
+    Ok(())
+}
 
-        #[cfg(not(target_os = "windows"))]
-        {
-            let _ = child.kill();
-        }
+/// Cancels a running download, deleting its partial files, and starts it
+/// again under the same id with a new format and optionally new flags.
+/// Options left out keep the old ones. The restarted download no longer
+/// counts against the queue's concurrency limit.
+#[tauri::command]
+pub async fn restart_download(
+    app: AppHandle,
+    id: String,
+    new_format_string: String,
+    subtitles: Option<bool>,
+    use_aria2c: Option<bool>,
+    options: Option<DownloadOptions>,
+) -> Result<(), CommandError> {
+    let is_playlist_video = ACTIVE_PLAYLISTS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .values()
+        .any(|video_id| video_id.as_deref() == Some(id.as_str()));
+    if is_playlist_video {
+        return Err(format!("Download {} is part of a playlist and can't be restarted", id).into());
+    }
+    let (mut job, mut finished) = RUNNING_DOWNLOADS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get(&id)
+        .map(|running| (running.job.clone(), running.finished.clone()))
+        .ok_or_else(|| format!("Download {} is not running", id))?;
+
+    job.format_string = new_format_string;
+    job.subtitles = subtitles.unwrap_or(job.subtitles);
+    job.use_aria2c = use_aria2c.unwrap_or(job.use_aria2c);
+    if let Some(mut options) = options {
+        // Credentials are never sent back by the frontend.
+        options.auth = options.auth.or(job.options.auth.take());
+        job.options = options;
     }
+    validate_format_ids(&app, &job.url, &job.options).await?;
 
+    stop_download(&id, false)?;
     let _ = app.emit(
         "download-status",
         serde_json::json!({
             "id": id,
-            "status": "cancelled",
+            "status": "restarting",
         }),
     );
+    // Errors once the old task drops its sender, after the temp dir is gone.
+    let _ = finished.changed().await;
+
+    // The old download is gone by now, so the UI must hear about a failed
+    // respawn rather than keep showing "restarting".
+    if let Err(error) = spawn_download(&app, job) {
+        let _ = app.emit(
+            "download-status",
+            serde_json::json!({
+                "id": id,
+                "status": "error",
+                "error": error.clone(),
+            }),
+        );
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Stops yt-dlp but keeps the partial files and the download task, so
+/// `resume_download` can pick up where it left off with the same args.
+#[tauri::command]
+pub fn pause_download(id: String) -> Result<(), String> {
+    // Mark the download as paused before killing it so its task doesn't
+    // mistake the exit for a cancellation.
+    PAUSED_DOWNLOADS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(id.clone(), None);
+
+    let child = ACTIVE_DOWNLOADS.lock().map_err(|e| e.to_string())?.remove(&id);
+    let Some(child) = child else {
+        if let Ok(mut paused_downloads) = PAUSED_DOWNLOADS.lock() {
+            paused_downloads.remove(&id);
+        }
+        return Err(format!("Download {} is not running", id));
+    };
 
+    clear_download_stats(&id);
+    kill_process_tree(child);
     Ok(())
 }
 
+#[tauri::command]
+pub fn resume_download(id: String) -> Result<(), String> {
+    let mut paused_downloads = PAUSED_DOWNLOADS.lock().map_err(|e| e.to_string())?;
+    match paused_downloads.remove(&id) {
+        Some(Some(resume_tx)) => resume_tx
+            .send(true)
+            .map_err(|_| format!("Download {} is no longer paused", id)),
+        Some(None) => {
+            paused_downloads.insert(id.clone(), None);
+            Err(format!("Download {} is still pausing", id))
+        }
+        None => Err(format!("Download {} is not paused", id)),
+    }
+}
+
+/// Pauses every running download; returns the ids that were paused.
+#[tauri::command]
+pub fn pause_all_downloads() -> Result<Vec<String>, String> {
+    let ids: Vec<String> = ACTIVE_DOWNLOADS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .keys()
+        .cloned()
+        .collect();
+
+    Ok(ids
+        .into_iter()
+        .filter(|id| pause_download(id.clone()).is_ok())
+        .collect())
+}
+
+/// Resumes every paused download; returns the ids that were resumed.
+#[tauri::command]
+pub fn resume_all_downloads() -> Result<Vec<String>, String> {
+    let ids: Vec<String> = PAUSED_DOWNLOADS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .filter(|(_, resume_tx)| resume_tx.is_some())
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    Ok(ids
+        .into_iter()
+        .filter(|id| resume_download(id.clone()).is_ok())
+        .collect())
+}
+
+/// Time to drain the running downloads and the queue at the current combined
+/// speed. Queued jobs count with their size from the cached format JSON; ones
+/// without a known size make the ETA a lower bound.
+#[tauri::command]
+pub fn get_aggregate_eta() -> AggregateEta {
+    let active_ids: Vec<String> = ACTIVE_DOWNLOADS
+        .lock()
+        .map(|downloads| downloads.keys().cloned().collect())
+        .unwrap_or_default();
+
+    let (mut remaining_bytes, speed_bytes_per_sec, missing_stats) = DOWNLOAD_STATS
+        .lock()
+        .map(|stats| {
+            let (remaining, speed) =
+                stats.values().fold((0u64, 0u64), |(remaining, speed), entry| {
+                    (
+                        remaining + entry.total_bytes.saturating_sub(entry.downloaded_bytes),
+                        speed + entry.speed_bytes_per_sec,
+                    )
+                });
+            // A download that hasn't printed progress yet has no size to count.
+            let missing = active_ids.iter().any(|id| !stats.contains_key(id));
+            (remaining, speed, missing)
+        })
+        .unwrap_or((0, 0, !active_ids.is_empty()));
+
+    let queued: Vec<(String, String)> = DOWNLOAD_QUEUE
+        .lock()
+        .map(|queue| {
+            queue
+                .pending
+                .iter()
+                .map(|job| (job.url.clone(), job.format_string.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut unknown_queued = 0;
+    for (url, format_string) in &queued {
+        match estimated_download_size(url, format_string) {
+            Some(size) => remaining_bytes += size,
+            None => unknown_queued += 1,
+        }
+    }
+
+    let eta_seconds = if missing_stats {
+        None
+    } else if remaining_bytes == 0 {
+        (unknown_queued == 0).then_some(0)
+    } else {
+        remaining_bytes.checked_div(speed_bytes_per_sec)
+    };
+
+    AggregateEta {
+        active_downloads: active_ids.len(),
+        queued_downloads: queued.len(),
+        remaining_bytes,
+        remaining_size_formatted: format_size(remaining_bytes, true),
+        speed_bytes_per_sec,
+        eta: eta_seconds.map(format_eta).unwrap_or_else(|| "N/A".to_string()),
+        eta_seconds,
+        eta_is_lower_bound: unknown_queued > 0,
+    }
+}
+
+#[tauri::command]
+pub fn open_in_browser(app: AppHandle, url: String) -> Result<(), String> {
+    let trimmed_url = url.trim();
+    if !(trimmed_url.starts_with("http://") || trimmed_url.starts_with("https://")) {
+        return Err("Only http and https URLs can be opened in the browser".to_string());
+    }
+
+    app.opener()
+        .open_url(trimmed_url, None::<&str>)
+        .map_err(|e| format!("Failed to open browser: {}", e))
+}
+
 #[tauri::command]
 pub async fn open_folder(path: String) -> Result<(), String> {
     #[cfg(target_os = "windows")]
@@ -783,3 +3661,147 @@ pub async fn open_folder(path: String) -> Result<(), String> {
     #[allow(unreachable_code)]
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quality(height: i32, fps: Option<f64>, vcodec: &str, video_size: u64) -> QualityOption {
+        QualityOption {
+            quality: format!("{}p", height),
+            height,
+            video_size,
+            audio_size: 0,
+            total_size: video_size,
+            total_size_formatted: format_size(video_size, false),
+            format_string: format!("{}-{}", vcodec, video_size),
+            has_combined_audio: false,
+            available: true,
+            video_size_estimated: false,
+            audio_size_estimated: false,
+            total_size_estimated: false,
+            size_breakdown: None,
+            fps,
+            vcodec: Some(vcodec.to_string()),
+            delivered_height: Some(height),
+        }
+    }
+
+    #[test]
+    fn dedup_keeps_largest_of_identical_qualities() {
+        let deduped = dedup_qualities(vec![
+            quality(1080, Some(30.0), "avc1", 100),
+            quality(720, Some(30.0), "avc1", 50),
+            quality(1080, Some(30.0), "avc1", 300),
+            quality(1080, Some(29.97), "avc1", 200),
+        ]);
+
+        assert_eq!(deduped.len(), 2);
+        assert_eq!(deduped[0].height, 1080);
+        assert_eq!(deduped[0].video_size, 300);
+        assert_eq!(deduped[1].height, 720);
+    }
+
+    #[test]
+    fn dedup_keeps_distinct_fps_and_codecs() {
+        let deduped = dedup_qualities(vec![
+            quality(1080, Some(30.0), "avc1", 100),
+            quality(1080, Some(60.0), "avc1", 150),
+            quality(1080, Some(30.0), "vp09", 90),
+        ]);
+
+        assert_eq!(deduped.len(), 3);
+    }
+
+    #[test]
+    fn dedup_keeps_unavailable_rows() {
+        let mut unavailable = quality(480, None, "avc1", 500);
+        unavailable.available = false;
+        let deduped = dedup_qualities(vec![unavailable, quality(480, None, "avc1", 10)]);
+
+        assert_eq!(deduped.len(), 2);
+        assert!(!deduped[0].available);
+        assert!(deduped[1].available);
+    }
+
+    fn video_format(format_id: &str, height: i64, fps: f64, vcodec: &str) -> serde_json::Value {
+        serde_json::json!({
+            "format_id": format_id,
+            "height": height,
+            "fps": fps,
+            "vcodec": vcodec,
+            "acodec": "none",
+            "vbr": height as f64 * 2.0,
+            "filesize": height * 1000,
+        })
+    }
+
+    fn formats_json(formats: Vec<serde_json::Value>) -> serde_json::Value {
+        let mut formats = formats;
+        formats.push(serde_json::json!({
+            "format_id": "140",
+            "vcodec": "none",
+            "acodec": "mp4a.40.2",
+            "abr": 128.0,
+            "filesize": 5000,
+        }));
+        serde_json::json!({ "duration": 60.0, "formats": formats })
+    }
+
+    fn height_rows(response: &FormatsResponse) -> Vec<&QualityOption> {
+        response
+            .qualities
+            .iter()
+            .filter(|quality| quality.format_string != BEST_QUALITY_FORMAT)
+            .collect()
+    }
+
+    #[test]
+    fn empty_buckets_show_the_nearest_height() {
+        let json = formats_json(vec![
+            video_format("137", 1080, 30.0, "avc1.640028"),
+            video_format("135", 480, 30.0, "avc1.4d401e"),
+        ]);
+        let response = build_formats_response(&json, Vec::new(), &FormatPreferences::default())
+            .unwrap();
+        let rows: Vec<(i32, bool, Option<i32>)> = height_rows(&response)
+            .iter()
+            .map(|quality| (quality.height, quality.available, quality.delivered_height))
+            .collect();
+
+        // 720p is as far from 1080p as from 480p; the tie goes to the higher one.
+        assert_eq!(
+            rows,
+            vec![
+                (1440, false, Some(1080)),
+                (1080, true, Some(1080)),
+                (720, false, Some(1080)),
+                (480, true, Some(480)),
+                (360, false, Some(480)),
+                (240, false, Some(480)),
+                (144, false, Some(480)),
+            ]
+        );
+    }
+
+    #[test]
+    fn distinct_formats_keep_their_rows() {
+        let json = formats_json(vec![
+            video_format("137", 1080, 30.0, "avc1.640028"),
+            video_format("136", 720, 30.0, "avc1.4d401f"),
+            video_format("135", 480, 30.0, "avc1.4d401e"),
+            video_format("134", 360, 30.0, "avc1.4d401e"),
+            video_format("133", 240, 30.0, "avc1.4d4015"),
+            video_format("160", 144, 30.0, "avc1.4d400c"),
+            video_format("271", 1440, 30.0, "vp9"),
+        ]);
+        let response = build_formats_response(&json, Vec::new(), &FormatPreferences::default())
+            .unwrap();
+        let heights: Vec<i32> = height_rows(&response)
+            .iter()
+            .map(|quality| quality.height)
+            .collect();
+
+        assert_eq!(heights, vec![1440, 1080, 720, 480, 360, 240, 144]);
+    }
+}
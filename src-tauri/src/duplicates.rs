@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager};
+
+use crate::downloads::cached_json_for_url;
+use crate::models::DownloadedVideo;
+use crate::state::DOWNLOADED_VIDEOS_LOCK;
+
+const DOWNLOADED_FILE_NAME: &str = "downloaded_videos.json";
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "mp4", "mkv", "webm", "mov", "m4a", "mp3", "opus", "ogg", "flac", "wav", "aac",
+];
+/// Durations within this many seconds count as the same video.
+const DURATION_TOLERANCE_SECS: f64 = 2.0;
+
+fn downloaded_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(data_dir.join(DOWNLOADED_FILE_NAME))
+}
+
+fn load_downloaded(app: &AppHandle) -> Vec<DownloadedVideo> {
+    downloaded_path(app)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_downloaded(app: &AppHandle, entries: &[DownloadedVideo]) -> Result<(), String> {
+    let path = downloaded_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+
+    let contents = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to save downloaded videos: {}", e))
+}
+
+/// YouTube video id from watch, short, shorts, live and embed URLs.
+pub fn video_id_from_url(url: &str) -> Option<String> {
+    let url = url.trim();
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = host.to_ascii_lowercase();
+    let path = path.split('#').next().unwrap_or(path);
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+
+    let id = if host.ends_with("youtu.be") {
+        path.split('/').next()
+    } else if host.ends_with("youtube.com") {
+        match path.split('/').collect::<Vec<_>>().as_slice() {
+            ["watch", ..] => query.split('&').find_map(|pair| pair.strip_prefix("v=")),
+            ["shorts" | "live" | "embed" | "v", id, ..] => Some(*id),
+            _ => None,
+        }
+    } else {
+        None
+    }?;
+    let valid = id.len() == 11
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| id.to_string())
+}
+
+/// Id, title and duration of `url`, from the cached `fetch_formats` JSON
+/// when there is one, otherwise just the id parsed from the URL.
+fn video_identity(url: &str) -> (Option<String>, Option<String>, Option<f64>) {
+    let json = cached_json_for_url(url);
+    let id = json
+        .as_ref()
+        .and_then(|json| json["id"].as_str().map(|id| id.to_string()))
+        .or_else(|| video_id_from_url(url));
+    let title = json
+        .as_ref()
+        .and_then(|json| json["title"].as_str().map(|title| title.to_string()));
+    let duration = json.as_ref().and_then(|json| json["duration"].as_f64());
+    (id, title, duration)
+}
+
+/// Remembers where `url` was saved, so later downloads of the same video
+/// can be flagged even when the output template has no `%(id)s`.
+pub fn record_downloaded_video(app: &AppHandle, url: &str, path: &Path) {
+    let (video_id, title, duration) = video_identity(url);
+    if video_id.is_none() && title.is_none() {
+        return;
+    }
+    let path = path.to_string_lossy().to_string();
+    // Downloads finish concurrently; don't let one save drop another's entry.
+    let _lock = DOWNLOADED_VIDEOS_LOCK.lock();
+    let mut entries = load_downloaded(app);
+    entries.retain(|entry| entry.path != path);
+    entries.push(DownloadedVideo {
+        video_id,
+        title,
+        duration,
+        path,
+        downloaded_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    });
+    if let Err(err) = save_downloaded(app, &entries) {
+        tracing::warn!("Failed to record downloaded video: {}", err);
+    }
+}
+
+/// Lowercased alphanumerics only, so yt-dlp's filename sanitizing doesn't
+/// hide a match.
+fn normalized_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn title_matches_in_dir(download_dir: &Path, title: &str) -> Option<PathBuf> {
+    let wanted = normalized_title(title);
+    if wanted.is_empty() {
+        return None;
+    }
+    std::fs::read_dir(download_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| {
+            let is_media = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| MEDIA_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+            is_media
+                && path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| normalized_title(stem) == wanted)
+        })
+}
+
+/// Existing file that is likely the same video as `url`: one recorded with
+/// the same id, or with the same title and duration, or a media file in
+/// `download_dir` named after the title.
+pub fn find_possible_duplicate(app: &AppHandle, url: &str, download_dir: &str) -> Option<String> {
+    let (video_id, title, duration) = video_identity(url);
+    let recorded = load_downloaded(app);
+    let existing = |entry: &&DownloadedVideo| Path::new(&entry.path).exists();
+
+    if let Some(video_id) = video_id.as_deref() {
+        if let Some(entry) = recorded
+            .iter()
+            .filter(existing)
+            .find(|entry| entry.video_id.as_deref() == Some(video_id))
+        {
+            return Some(entry.path.clone());
+        }
+    }
+
+    let title = title?;
+    let same_title_and_duration = recorded.iter().filter(existing).find(|entry| {
+        entry.title.as_deref() == Some(title.as_str())
+            && match (entry.duration, duration) {
+                (Some(a), Some(b)) => (a - b).abs() <= DURATION_TOLERANCE_SECS,
+                _ => false,
+            }
+    });
+    if let Some(entry) = same_title_and_duration {
+        return Some(entry.path.clone());
+    }
+
+    title_matches_in_dir(Path::new(download_dir), &title)
+        .map(|path| path.to_string_lossy().to_string())
+}
@@ -0,0 +1,195 @@
+use serde::Serialize;
+
+/// Error returned to the frontend as `{ "kind": "...", "message": "..." }` so the
+/// UI can react to specific failures instead of pattern-matching message text.
+#[derive(Clone, Serialize, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CommandError {
+    AuthFailed { message: String },
+    /// Permanent: the content is DRM protected, so retrying can't help.
+    DrmProtected { message: String },
+    VideoPrivate { message: String },
+    /// Taken down by the uploader or the site, or the channel was terminated.
+    VideoRemoved { message: String },
+    MembersOnly { message: String },
+    RegionLocked { message: String },
+    /// The site gave no more specific reason than "unavailable".
+    VideoUnavailable { message: String },
+    /// yt-dlp found no JavaScript runtime to solve YouTube's challenges.
+    JsRuntimeMissing { message: String },
+    /// A directory we need to write to rejected a test write.
+    PermissionDenied { message: String, path: Option<String> },
+    /// The disk ran out of space; `path` is the download or temp folder that
+    /// filled up.
+    DiskFull { message: String, path: Option<String> },
+    /// The video seems to be downloaded already; `path` is the existing file.
+    /// Sending `allow_duplicate` downloads it anyway.
+    PossibleDuplicate { message: String, path: String },
+    /// The format selection matched nothing the site offers now. A fresh
+    /// listing follows as a `download-formats` event when one can be fetched.
+    FormatUnavailable { message: String },
+    Other { message: String },
+}
+
+impl CommandError {
+    /// Whether trying the same request again could succeed.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            CommandError::DrmProtected { .. }
+                | CommandError::VideoPrivate { .. }
+                | CommandError::VideoRemoved { .. }
+                | CommandError::MembersOnly { .. }
+                | CommandError::RegionLocked { .. }
+                | CommandError::JsRuntimeMissing { .. }
+                // Not until the user frees some space.
+                | CommandError::DiskFull { .. }
+                // The same selection fails the same way.
+                | CommandError::FormatUnavailable { .. }
+        )
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            CommandError::AuthFailed { message }
+            | CommandError::DrmProtected { message }
+            | CommandError::VideoPrivate { message }
+            | CommandError::VideoRemoved { message }
+            | CommandError::MembersOnly { message }
+            | CommandError::RegionLocked { message }
+            | CommandError::VideoUnavailable { message }
+            | CommandError::JsRuntimeMissing { message }
+            | CommandError::PermissionDenied { message, .. }
+            | CommandError::DiskFull { message, .. }
+            | CommandError::PossibleDuplicate { message, .. }
+            | CommandError::FormatUnavailable { message }
+            | CommandError::Other { message } => message,
+        }
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Other { message }
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        CommandError::Other {
+            message: message.to_string(),
+        }
+    }
+}
+
+fn is_auth_failure(lower: &str) -> bool {
+    [
+        "invalid username",
+        "invalid password",
+        "incorrect password",
+        "wrong password",
+        "invalid credentials",
+        "login failed",
+        "unable to login",
+        "unable to log in",
+        "authentication failed",
+        "two-factor",
+        "2fa",
+    ]
+    .iter()
+    .any(|pattern| lower.contains(pattern))
+}
+
+/// Why a video can't be fetched, judged from yt-dlp's message. The specific
+/// reasons are checked before the generic "unavailable" that often precedes them.
+fn unavailability_reason(lower: &str) -> Option<fn(String) -> CommandError> {
+    let matches_any =
+        |patterns: &[&str]| patterns.iter().any(|pattern| lower.contains(pattern));
+
+    if matches_any(&["private video", "this video is private"]) {
+        Some(|message| CommandError::VideoPrivate { message })
+    } else if matches_any(&[
+        "has been removed",
+        "account associated with this video has been terminated",
+        "video has been deleted",
+        "video is no longer available because",
+        "video is no longer available due to a copyright claim",
+    ]) {
+        Some(|message| CommandError::VideoRemoved { message })
+    } else if matches_any(&["members-only", "channel's members", "join this channel"]) {
+        Some(|message| CommandError::MembersOnly { message })
+    } else if matches_any(&["in your country", "geo restrict", "geo-restrict"]) {
+        Some(|message| CommandError::RegionLocked { message })
+    } else if matches_any(&["video unavailable", "video is unavailable"]) {
+        Some(|message| CommandError::VideoUnavailable { message })
+    } else {
+        None
+    }
+}
+
+/// ENOSPC from Python/ffmpeg, or Windows' ERROR_DISK_FULL text.
+pub fn is_disk_full(lower: &str) -> bool {
+    lower.contains("no space left on device")
+        || lower.contains("there is not enough space")
+        || lower.contains("[errno 28]")
+}
+
+/// Maps yt-dlp stderr to the most specific error variant we recognise.
+pub fn classify_ytdlp_error(stderr: &str, context: &str) -> CommandError {
+    let message = format!("{}: {}", context, stderr.trim());
+    let lower = stderr.to_ascii_lowercase();
+
+    if is_auth_failure(&lower) {
+        return CommandError::AuthFailed { message };
+    }
+
+    if lower.contains("drm protected") || lower.contains("drm-protected") {
+        return CommandError::DrmProtected {
+            message: format!(
+                "This content is DRM protected and cannot be downloaded ({})",
+                stderr.trim()
+            ),
+        };
+    }
+
+    // Without a runtime YouTube can also report the video as unavailable.
+    if lower.contains("supported javascript runtime") {
+        return CommandError::JsRuntimeMissing {
+            message: format!(
+                "YouTube needs a JavaScript runtime: install Node.js or choose deno/bun \
+                 in settings ({})",
+                stderr.trim()
+            ),
+        };
+    }
+
+    if let Some(reason) = unavailability_reason(&lower) {
+        return reason(message);
+    }
+
+    if lower.contains("permission denied") || lower.contains("[errno 13]") {
+        return CommandError::PermissionDenied {
+            message,
+            path: None,
+        };
+    }
+
+    if is_disk_full(&lower) {
+        return CommandError::DiskFull {
+            message,
+            path: None,
+        };
+    }
+
+    if lower.contains("requested format is not available") {
+        return CommandError::FormatUnavailable { message };
+    }
+
+    CommandError::Other { message }
+}
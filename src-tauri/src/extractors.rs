@@ -0,0 +1,216 @@
+use tauri::AppHandle;
+
+use crate::models::{ExtractorArgOption, ExtractorArgsHelp};
+use crate::runtime::ytdlp_command;
+use crate::state::EXTRACTOR_CACHE;
+
+/// (name, description, example)
+type ExtractorArgDoc = (&'static str, &'static str, &'static str);
+
+const EXTRACTOR_ARGS_DOCS_URL: &str = "https://github.com/yt-dlp/yt-dlp#extractor-arguments";
+
+/// yt-dlp has no machine-readable listing of extractor args, so this mirrors
+/// the commonly used ones from its README. Keys are lowercase `--extractor-args`
+/// namespaces.
+const KNOWN_EXTRACTOR_ARGS: &[(&str, &[ExtractorArgDoc])] = &[
+    (
+        "youtube",
+        &[
+            (
+                "player_client",
+                "Clients used to fetch formats; tv_embedded or web_safari often pass age gates",
+                "youtube:player_client=default,web_safari",
+            ),
+            (
+                "player_skip",
+                "Skip extraction steps such as the webpage, configs or js player",
+                "youtube:player_skip=webpage,configs",
+            ),
+            (
+                "skip",
+                "Skip hls, dash or translated_subs formats/subtitles",
+                "youtube:skip=dash",
+            ),
+            (
+                "lang",
+                "Preferred language for translated metadata",
+                "youtube:lang=en",
+            ),
+            (
+                "comment_sort",
+                "Sort comments by top or new",
+                "youtube:comment_sort=top",
+            ),
+            (
+                "max_comments",
+                "Limit comments fetched: total, parents, replies, replies per thread",
+                "youtube:max_comments=500,all,100,10",
+            ),
+            (
+                "po_token",
+                "Proof-of-origin token(s) per client, for bot-check failures",
+                "youtube:po_token=web.gvs+TOKEN",
+            ),
+            (
+                "formats",
+                "Change which formats are returned, e.g. include missing_pot formats",
+                "youtube:formats=missing_pot",
+            ),
+        ],
+    ),
+    (
+        "youtubetab",
+        &[
+            (
+                "skip",
+                "Skip webpage or authcheck when extracting channels and playlists",
+                "youtubetab:skip=authcheck",
+            ),
+            (
+                "approximate_date",
+                "Extract approximate upload dates for flat playlist entries",
+                "youtubetab:approximate_date",
+            ),
+        ],
+    ),
+    (
+        "generic",
+        &[
+            (
+                "fragment_query",
+                "Pass the page URL query string to each fragment request",
+                "generic:fragment_query",
+            ),
+            (
+                "variant_query",
+                "Pass the page URL query string to HLS/DASH variant manifests",
+                "generic:variant_query",
+            ),
+            (
+                "impersonate",
+                "Impersonate a browser for the initial webpage request",
+                "generic:impersonate=chrome",
+            ),
+        ],
+    ),
+    (
+        "twitter",
+        &[(
+            "api",
+            "API to use: graphql, legacy or syndication",
+            "twitter:api=syndication",
+        )],
+    ),
+    (
+        "tiktok",
+        &[
+            (
+                "api_hostname",
+                "Hostname of the mobile API to use",
+                "tiktok:api_hostname=api22-normal-c-useast2a.tiktokv.com",
+            ),
+            (
+                "app_info",
+                "Mobile app info used for API requests",
+                "tiktok:app_info=...",
+            ),
+        ],
+    ),
+    (
+        "instagram",
+        &[(
+            "app_id",
+            "App id sent with API requests",
+            "instagram:app_id=936619743392459",
+        )],
+    ),
+    (
+        "vimeo",
+        &[(
+            "client",
+            "Client used for API requests: android, ios or web",
+            "vimeo:client=android",
+        )],
+    ),
+];
+
+pub fn clear_extractor_cache() {
+    if let Ok(mut cache) = EXTRACTOR_CACHE.lock() {
+        *cache = None;
+    }
+}
+
+async fn load_extractors(app: &AppHandle) -> Result<Vec<String>, String> {
+    if let Ok(cache) = EXTRACTOR_CACHE.lock() {
+        if let Some(extractors) = cache.as_ref() {
+            return Ok(extractors.clone());
+        }
+    }
+
+    let sidecar_command = ytdlp_command(app).map_err(|e| e.to_string())?;
+    let output = sidecar_command
+        .args(vec!["--list-extractors"])
+        .output()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to list extractors: {}", stderr));
+    }
+
+    let extractors: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    if let Ok(mut cache) = EXTRACTOR_CACHE.lock() {
+        *cache = Some(extractors.clone());
+    }
+
+    Ok(extractors)
+}
+
+#[tauri::command]
+pub async fn list_extractors(app: AppHandle, query: Option<String>) -> Result<Vec<String>, String> {
+    let extractors = load_extractors(&app).await?;
+
+    let query = query
+        .map(|query| query.trim().to_lowercase())
+        .filter(|query| !query.is_empty());
+
+    Ok(match query {
+        Some(query) => extractors
+            .into_iter()
+            .filter(|name| name.to_lowercase().contains(&query))
+            .collect(),
+        None => extractors,
+    })
+}
+
+/// Returns the documented `--extractor-args` options for `extractor`.
+/// Unknown extractors get an empty list plus the link to yt-dlp's docs.
+#[tauri::command]
+pub fn get_extractor_args_help(extractor: String) -> ExtractorArgsHelp {
+    let key = extractor.trim().to_lowercase();
+    let args = KNOWN_EXTRACTOR_ARGS
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, args)| {
+            args.iter()
+                .map(|(name, description, example)| ExtractorArgOption {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    example: example.to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    ExtractorArgsHelp {
+        extractor: key,
+        args,
+        documentation_url: EXTRACTOR_ARGS_DOCS_URL.to_string(),
+    }
+}
@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager};
+
+use crate::models::{PlaylistJournalEntry, ResumableDownload};
+use crate::state::JOURNAL_LOCK;
+
+const JOURNAL_FILE_NAME: &str = "resumable_downloads.json";
+const PLAYLIST_JOURNAL_FILE_NAME: &str = "playlist_downloads.json";
+const PLAYLIST_ARCHIVE_DIR_NAME: &str = "playlist_archives";
+
+fn journal_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(data_dir.join(JOURNAL_FILE_NAME))
+}
+
+fn load_journal(app: &AppHandle) -> Vec<ResumableDownload> {
+    journal_path(app)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_journal(app: &AppHandle, entries: &[ResumableDownload]) -> Result<(), String> {
+    let path = journal_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create journal directory: {}", e))?;
+    }
+
+    let contents = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to save journal: {}", e))
+}
+
+/// Records a download whose partial files were kept, replacing any older
+/// entry with the same id.
+pub fn record_resumable_download(app: &AppHandle, entry: ResumableDownload) {
+    let _lock = JOURNAL_LOCK.lock();
+    let mut entries = load_journal(app);
+    entries.retain(|existing| existing.id != entry.id);
+    entries.push(entry);
+    if let Err(err) = save_journal(app, &entries) {
+        tracing::warn!("Failed to record resumable download: {}", err);
+    }
+}
+
+/// Forgets `id`, e.g. because a download with that id was started again.
+pub fn remove_resumable_download(app: &AppHandle, id: &str) {
+    let _lock = JOURNAL_LOCK.lock();
+    let mut entries = load_journal(app);
+    let before = entries.len();
+    entries.retain(|entry| entry.id != id);
+    if entries.len() != before {
+        if let Err(err) = save_journal(app, &entries) {
+            tracing::warn!("Failed to update resumable downloads: {}", err);
+        }
+    }
+}
+
+/// Downloads cancelled with `keep_partial`. Calling `start_download` with the
+/// same id, url and folder resumes from the kept `.part` files.
+#[tauri::command]
+pub fn list_resumable_downloads(app: AppHandle) -> Vec<ResumableDownload> {
+    load_journal(&app)
+        .into_iter()
+        .filter(|entry| PathBuf::from(&entry.temp_dir).exists())
+        .collect()
+}
+
+/// Deletes the kept partial files for `id` and drops it from the journal.
+#[tauri::command]
+pub fn discard_resumable_download(app: AppHandle, id: String) -> Result<(), String> {
+    let entries = load_journal(&app);
+    if let Some(entry) = entries.iter().find(|entry| entry.id == id) {
+        let temp_dir = PathBuf::from(&entry.temp_dir);
+        if temp_dir.exists() {
+            std::fs::remove_dir_all(&temp_dir)
+                .map_err(|e| format!("Failed to delete partial files: {}", e))?;
+        }
+    }
+    remove_resumable_download(&app, &id);
+    Ok(())
+}
+
+fn playlist_journal_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(data_dir.join(PLAYLIST_JOURNAL_FILE_NAME))
+}
+
+fn load_playlist_journal(app: &AppHandle) -> Vec<PlaylistJournalEntry> {
+    playlist_journal_path(app)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_playlist_journal(app: &AppHandle, entries: &[PlaylistJournalEntry]) -> Result<(), String> {
+    let path = playlist_journal_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create journal directory: {}", e))?;
+    }
+
+    let contents = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to save playlist journal: {}", e))
+}
+
+/// Saves how far `entry`'s playlist got, replacing its previous state.
+pub fn record_playlist_progress(app: &AppHandle, entry: &PlaylistJournalEntry) {
+    let _lock = JOURNAL_LOCK.lock();
+    let mut entries = load_playlist_journal(app);
+    entries.retain(|existing| existing.playlist_id != entry.playlist_id);
+    entries.push(entry.clone());
+    if let Err(err) = save_playlist_journal(app, &entries) {
+        tracing::warn!("Failed to record playlist progress: {}", err);
+    }
+}
+
+pub fn find_playlist_journal(app: &AppHandle, playlist_id: &str) -> Option<PlaylistJournalEntry> {
+    load_playlist_journal(app)
+        .into_iter()
+        .find(|entry| entry.playlist_id == playlist_id)
+}
+
+/// Forgets a finished playlist along with its download archive.
+pub fn remove_playlist_journal(app: &AppHandle, playlist_id: &str) {
+    let _lock = JOURNAL_LOCK.lock();
+    let mut entries = load_playlist_journal(app);
+    let before = entries.len();
+    entries.retain(|entry| entry.playlist_id != playlist_id);
+    if entries.len() != before {
+        if let Err(err) = save_playlist_journal(app, &entries) {
+            tracing::warn!("Failed to update playlist journal: {}", err);
+        }
+    }
+    if let Ok(archive) = playlist_archive_path(app, playlist_id) {
+        let _ = std::fs::remove_file(archive);
+    }
+}
+
+/// `--download-archive` file for a playlist, named after a hex form of its
+/// id so any id is a valid file name.
+pub fn playlist_archive_path(app: &AppHandle, playlist_id: &str) -> Result<PathBuf, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let file_name: String = playlist_id.bytes().map(|b| format!("{:02x}", b)).collect();
+    Ok(data_dir
+        .join(PLAYLIST_ARCHIVE_DIR_NAME)
+        .join(format!("{}.txt", file_name)))
+}
+
+/// Playlist downloads that were interrupted or left entries failed.
+#[tauri::command]
+pub fn list_resumable_playlists(app: AppHandle) -> Vec<PlaylistJournalEntry> {
+    load_playlist_journal(&app)
+}
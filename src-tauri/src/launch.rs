@@ -0,0 +1,73 @@
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+use regex::Regex;
+use tauri::{AppHandle, Emitter};
+
+use crate::models::LaunchRequest;
+use crate::state::{LAUNCH_URLS_TAKEN, PENDING_LAUNCH_URLS};
+
+/// Passing this flag next to the URL asks the UI to start the download right
+/// away instead of only pre-filling it.
+const AUTO_START_FLAG: &str = "--download";
+
+/// The URL inside an internet shortcut the OS opened us with: `URL=` in a
+/// Windows `.url` file, or the `<string>` of a macOS `.webloc` plist.
+fn shortcut_url(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let contents = std::fs::read_to_string(path).ok()?;
+    let url = match extension.as_str() {
+        "url" => contents
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("URL="))
+            .map(str::to_string),
+        "webloc" => Regex::new(r"<string>\s*([^<]+?)\s*</string>")
+            .unwrap()
+            .captures(&contents)
+            .map(|caps| caps[1].to_string()),
+        _ => None,
+    }?;
+    (url.starts_with("http://") || url.starts_with("https://")).then_some(url)
+}
+
+/// Picks http(s) URLs, and shortcut files from a file association, out of a
+/// command line (ours at startup, or one forwarded by the single-instance
+/// plugin) and hands them to the frontend. Until the frontend has called
+/// `take_launch_urls` they are only queued, since the webview may not be
+/// listening yet; after that they are only emitted.
+pub fn handle_launch_args(app: &AppHandle, args: &[String]) {
+    let auto_start = args.iter().any(|arg| arg == AUTO_START_FLAG);
+    let requests: Vec<LaunchRequest> = args
+        .iter()
+        .map(|arg| arg.trim())
+        .filter_map(|arg| {
+            if arg.starts_with("http://") || arg.starts_with("https://") {
+                Some(arg.to_string())
+            } else {
+                shortcut_url(Path::new(arg))
+            }
+        })
+        .map(|url| LaunchRequest { url, auto_start })
+        .collect();
+
+    for request in requests {
+        if let Ok(mut pending) = PENDING_LAUNCH_URLS.lock() {
+            if !LAUNCH_URLS_TAKEN.load(Ordering::Relaxed) {
+                pending.push(request);
+                continue;
+            }
+        }
+        let _ = app.emit("launch-url", request);
+    }
+}
+
+#[tauri::command]
+pub fn take_launch_urls() -> Vec<LaunchRequest> {
+    match PENDING_LAUNCH_URLS.lock() {
+        Ok(mut pending) => {
+            LAUNCH_URLS_TAKEN.store(true, Ordering::Relaxed);
+            std::mem::take(&mut *pending)
+        }
+        Err(_) => Vec::new(),
+    }
+}
@@ -1,18 +1,73 @@
+mod args;
+mod backup;
+mod batch;
 mod bridge;
+mod browsers;
+mod cookies;
+mod diagnostics;
 mod downloads;
+mod duplicates;
+mod errors;
+mod extractors;
+mod journal;
+mod launch;
+mod logging;
+mod media;
 mod models;
+mod playlists;
+mod process;
+mod queue;
+mod ratelimit;
+mod runtime;
+mod settings;
 mod state;
+mod storage;
+mod storyboards;
+mod streaming;
+mod subtitles;
 mod tray;
 mod updates;
 
 use tauri::WindowEvent;
 use tauri_plugin_single_instance::init as single_instance;
 
+use backup::{export_state, import_state};
+use batch::import_url_list;
 use bridge::{get_extension_bridge_info, start_extension_bridge, take_extension_download_requests};
-use downloads::{cancel_download, fetch_formats, fetch_playlist_info, open_folder, start_download};
+use browsers::list_browser_profiles;
+use cookies::{clear_cookie_cache, init_cookie_cache, remove_cookie_cache};
+use diagnostics::{benchmark_download_method, diagnose_youtube, get_versions};
+use downloads::{
+    cancel_download, clear_format_cache, fetch_audio_formats, fetch_chapters, fetch_formats,
+    fetch_formats_batch, get_aggregate_eta, get_exact_size, open_folder, open_in_browser,
+    pause_all_downloads, pause_download, preview_command, reselect_quality, restart_download,
+    resume_all_downloads, resume_download, start_download, validate_format_string,
+};
+use extractors::{get_extractor_args_help, list_extractors};
+use journal::{discard_resumable_download, list_resumable_downloads, list_resumable_playlists};
+use launch::{handle_launch_args, take_launch_urls};
+use logging::{init_logging, open_log_file};
+use media::{remux_parts, remux_with_offset};
+use playlists::{
+    fetch_playlist_info, fetch_playlist_page, resume_playlist, start_playlist_download,
+};
+use process::cancel_fetch;
+use queue::{
+    enqueue_download, force_start_download, get_queue_state, pause_queue, requeue_download,
+    resume_queue,
+};
+use runtime::{
+    check_js_runtime, check_js_runtime_on_startup, get_js_runtime_status, test_custom_ytdlp,
+    warm_remote_components,
+};
+use settings::{get_default_quality, get_settings, load_settings, update_settings};
 use state::MAIN_WINDOW_LABEL;
+use storage::get_downloads_size;
+use storyboards::{download_storyboard, list_storyboards};
+use streaming::{get_direct_url, start_stream};
+use subtitles::preview_subtitles;
 use tray::{create_tray, restore_main_window};
-use updates::{check_ytdlp_update, update_ytdlp};
+use updates::{check_ytdlp_update, check_ytdlp_update_on_startup, update_ytdlp};
 
 #[tauri::command]
 fn exit_app(app: tauri::AppHandle) {
@@ -22,12 +77,20 @@ fn exit_app(app: tauri::AppHandle) {
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
-        .plugin(single_instance(|app, _args, _cwd| {
+        .plugin(single_instance(|app, args, _cwd| {
             restore_main_window(app);
+            handle_launch_args(app, &args);
         }))
         .setup(|app| {
+            init_logging(app.handle());
+            load_settings(app.handle());
+            init_cookie_cache(app.handle());
+            let launch_args: Vec<String> = std::env::args().skip(1).collect();
+            handle_launch_args(app.handle(), &launch_args);
+            check_js_runtime_on_startup(app.handle());
+            check_ytdlp_update_on_startup(app.handle());
             start_extension_bridge(app.handle().clone());
-            create_tray(&app.handle())?;
+            create_tray(app.handle())?;
             Ok(())
         })
         .on_window_event(|window, event| {
@@ -47,15 +110,75 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             start_download,
             fetch_formats,
+            fetch_audio_formats,
             fetch_playlist_info,
             cancel_download,
+            cancel_fetch,
+            get_aggregate_eta,
             check_ytdlp_update,
             update_ytdlp,
             open_folder,
+            open_in_browser,
+            get_downloads_size,
             get_extension_bridge_info,
             take_extension_download_requests,
+            import_url_list,
+            list_extractors,
+            get_settings,
+            update_settings,
+            benchmark_download_method,
+            start_playlist_download,
+            clear_format_cache,
+            list_browser_profiles,
+            get_extractor_args_help,
+            pause_download,
+            resume_download,
+            pause_all_downloads,
+            resume_all_downloads,
+            validate_format_string,
+            start_stream,
+            fetch_formats_batch,
+            get_default_quality,
+            remux_parts,
+            clear_cookie_cache,
+            take_launch_urls,
+            enqueue_download,
+            pause_queue,
+            resume_queue,
+            get_queue_state,
+            list_resumable_downloads,
+            discard_resumable_download,
+            get_versions,
+            reselect_quality,
+            export_state,
+            import_state,
+            get_direct_url,
+            list_storyboards,
+            download_storyboard,
+            diagnose_youtube,
+            check_js_runtime,
+            warm_remote_components,
+            preview_command,
+            open_log_file,
+            force_start_download,
+            requeue_download,
+            get_exact_size,
+            restart_download,
+            resume_playlist,
+            list_resumable_playlists,
+            preview_subtitles,
+            fetch_chapters,
+            remux_with_offset,
+            fetch_playlist_page,
+            test_custom_ytdlp,
+            get_js_runtime_status,
             exit_app
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                remove_cookie_cache();
+            }
+        });
 }
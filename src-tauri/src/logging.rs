@@ -0,0 +1,144 @@
+use std::io::Write;
+use std::path::Path;
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_opener::OpenerExt;
+use tracing::Level;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, reload};
+
+use crate::models::AppSettings;
+use crate::settings::current_settings;
+use crate::state::{LOG_FILE, LogFile};
+
+const LOG_FILE_PREFIX: &str = "dlpgui";
+const LOG_FILE_SUFFIX: &str = "log";
+const DEFAULT_LOG_LEVEL: Level = Level::INFO;
+const DEFAULT_LOG_MAX_FILES: usize = 3;
+const LOG_LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+fn parse_log_level(level: Option<&str>) -> Result<Level, String> {
+    let Some(level) = level.map(str::trim).filter(|level| !level.is_empty()) else {
+        return Ok(DEFAULT_LOG_LEVEL);
+    };
+    level.parse::<Level>().map_err(|_| {
+        format!(
+            "Unsupported log level {:?}; use one of {}",
+            level,
+            LOG_LEVELS.join(", ")
+        )
+    })
+}
+
+pub fn validate_log_settings(settings: &AppSettings) -> Result<(), String> {
+    parse_log_level(settings.log_level.as_deref())?;
+    if settings.log_max_files == Some(0) {
+        return Err("At least one log file must be kept".to_string());
+    }
+    Ok(())
+}
+
+/// Only this crate's events at the configured level, unless `RUST_LOG` says
+/// otherwise.
+fn log_filter(settings: &AppSettings) -> EnvFilter {
+    let level = parse_log_level(settings.log_level.as_deref()).unwrap_or(DEFAULT_LOG_LEVEL);
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(format!("{}={}", env!("CARGO_CRATE_NAME"), level))
+    })
+}
+
+fn log_appender(dir: &Path, settings: &AppSettings) -> Result<RollingFileAppender, String> {
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(settings.log_max_files.unwrap_or(DEFAULT_LOG_MAX_FILES).max(1))
+        .build(dir)
+        .map_err(|e| format!("Failed to open log file: {}", e))
+}
+
+/// Picks up level and retention changes without restarting.
+pub fn apply_log_settings(settings: &AppSettings) {
+    let Ok(mut log_file) = LOG_FILE.lock() else {
+        return;
+    };
+    let Some(log_file) = log_file.as_mut() else {
+        return;
+    };
+    let _ = log_file.filter.reload(log_filter(settings));
+    if let Ok(appender) = log_appender(&log_file.dir, settings) {
+        log_file.appender = appender;
+    }
+}
+
+/// Hands formatted lines to whichever appender `LOG_FILE` holds, so
+/// `apply_log_settings` can swap it.
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match LOG_FILE.lock() {
+            Ok(mut log_file) => match log_file.as_mut() {
+                Some(log_file) => log_file.appender.write(buf),
+                None => Ok(buf.len()),
+            },
+            Err(_) => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match LOG_FILE.lock() {
+            Ok(mut log_file) => match log_file.as_mut() {
+                Some(log_file) => log_file.appender.flush(),
+                None => Ok(()),
+            },
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// Routes this crate's `tracing` events to daily files in the app log dir.
+/// Args are logged through `redact_args`, so credentials never reach them.
+pub fn init_logging(app: &AppHandle) {
+    let Ok(dir) = app.path().app_log_dir() else {
+        return;
+    };
+    if std::fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    let settings = current_settings();
+    let Ok(appender) = log_appender(&dir, &settings) else {
+        return;
+    };
+    let (filter, handle) = reload::Layer::new(log_filter(&settings));
+    if let Ok(mut log_file) = LOG_FILE.lock() {
+        *log_file = Some(LogFile {
+            dir,
+            appender,
+            filter: handle,
+        });
+    }
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(|| LogWriter),
+        )
+        .try_init();
+}
+
+#[tauri::command]
+pub fn open_log_file(app: AppHandle) -> Result<(), String> {
+    let dir = LOG_FILE
+        .lock()
+        .map_err(|e| e.to_string())?
+        .as_ref()
+        .map(|log_file| log_file.dir.clone())
+        .ok_or("File logging is not available")?;
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open log folder: {}", e))
+}
@@ -0,0 +1,403 @@
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use tauri::AppHandle;
+use tauri_plugin_shell::ShellExt;
+
+/// Demuxer names ffmpeg prints in `Input #0, <names>, from ...`, mapped to
+/// the extensions that are valid for that container. The first extension is
+/// used when renaming.
+const CONTAINER_EXTENSIONS: &[(&str, &[&str])] = &[
+    (
+        "mov,mp4,m4a,3gp,3g2,mj2",
+        &["mp4", "m4a", "mov", "m4v", "3gp"],
+    ),
+    ("matroska,webm", &["mkv", "webm", "mka"]),
+    ("mp3", &["mp3"]),
+    ("ogg", &["ogg", "opus", "oga"]),
+    ("flac", &["flac"]),
+    ("wav", &["wav"]),
+    ("aac", &["aac"]),
+    ("mpegts", &["ts"]),
+    ("flv", &["flv"]),
+    ("avi", &["avi"]),
+];
+
+/// Codecs WebM allows; a Matroska file using only these is named `.webm`.
+const WEBM_CODECS: &[&str] = &["vp8", "vp9", "av1", "opus", "vorbis"];
+
+pub struct ProbedContainer {
+    pub demuxer: String,
+    pub codecs: Vec<String>,
+}
+
+/// Reads the container and stream codecs of `path` from `ffmpeg -i`, which
+/// prints the input summary and exits because no output was given.
+pub async fn probe_container(app: &AppHandle, path: &Path) -> Result<ProbedContainer, String> {
+    let output = app
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| e.to_string())?
+        .args([
+            "-hide_banner".to_string(),
+            "-i".to_string(),
+            path.to_string_lossy().to_string(),
+        ])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let re_input = Regex::new(r"Input #0, (.+?), from").unwrap();
+    let re_stream = Regex::new(r"Stream #0:\d+.*?: (?:Video|Audio): (\w+)").unwrap();
+
+    let demuxer = re_input
+        .captures(&stderr)
+        .map(|caps| caps[1].to_string())
+        .ok_or_else(|| format!("ffmpeg could not read {:?}", path))?;
+    let codecs = re_stream
+        .captures_iter(&stderr)
+        .map(|caps| caps[1].to_ascii_lowercase())
+        .collect();
+
+    Ok(ProbedContainer { demuxer, codecs })
+}
+
+/// Checks that `path`'s extension matches its real container and renames the
+/// file when it doesn't. Returns the new path if the file was renamed.
+pub async fn verify_output_container(
+    app: &AppHandle,
+    path: &Path,
+) -> Result<Option<PathBuf>, String> {
+    let probed = probe_container(app, path).await?;
+    let Some((_, extensions)) = CONTAINER_EXTENSIONS
+        .iter()
+        .find(|(demuxer, _)| *demuxer == probed.demuxer)
+    else {
+        // Unknown container; nothing reliable to compare against.
+        return Ok(None);
+    };
+
+    let current_ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    if extensions.contains(&current_ext.as_str()) {
+        return Ok(None);
+    }
+
+    let is_matroska = probed.demuxer == "matroska,webm";
+    let expected_ext = if is_matroska
+        && probed
+            .codecs
+            .iter()
+            .all(|codec| WEBM_CODECS.contains(&codec.as_str()))
+    {
+        "webm"
+    } else {
+        extensions[0]
+    };
+
+    let renamed = path.with_extension(expected_ext);
+    if renamed.exists() {
+        return Err(format!(
+            "{:?} is really a {} file but {:?} already exists",
+            path, expected_ext, renamed
+        ));
+    }
+    std::fs::rename(path, &renamed)
+        .map_err(|e| format!("Failed to rename {:?} to {:?}: {}", path, renamed, e))?;
+    Ok(Some(renamed))
+}
+
+/// Image formats ffmpeg can store as cover art in every supported container.
+const COVER_IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png"];
+/// Containers `embed_cover_art` can add a cover to; webm and opus have no
+/// cover art stream ffmpeg can write.
+const COVER_ART_CONTAINERS: &[&str] = &["mp4", "m4a", "m4v", "mov", "mp3", "mkv", "mka"];
+
+/// Checks that `path` is an existing jpg or png image usable as cover art,
+/// and that `container` (when it is known up front) can carry it.
+pub fn validate_cover_image(path: &str, container: Option<&str>) -> Result<PathBuf, String> {
+    if let Some(container) = container.filter(|ext| !COVER_ART_CONTAINERS.contains(ext)) {
+        return Err(format!(
+            "A custom thumbnail can't be embedded in .{} files; pick an mp4 or mkv container",
+            container
+        ));
+    }
+    let image = PathBuf::from(path.trim());
+    if !image.is_file() {
+        return Err(format!("Thumbnail image {:?} does not exist", image));
+    }
+    let ext = image
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    if !COVER_IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+        return Err(format!(
+            "Unsupported thumbnail format {:?}; use a jpg or png image",
+            ext
+        ));
+    }
+    Ok(image)
+}
+
+/// Replaces the cover art of `media` with `image`, remuxing with stream copy
+/// into a temporary file next to it.
+pub async fn embed_cover_art(app: &AppHandle, media: &Path, image: &Path) -> Result<(), String> {
+    let media_ext = media
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let image_ext = image
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let mime_type = if image_ext == "png" {
+        "image/png"
+    } else {
+        "image/jpeg"
+    };
+    let media_arg = media.to_string_lossy().to_string();
+    let image_arg = image.to_string_lossy().to_string();
+    let mut args = vec!["-hide_banner".to_string(), "-y".to_string()];
+
+    if !COVER_ART_CONTAINERS.contains(&media_ext.as_str()) {
+        return Err(format!(
+            "Cover art can't be embedded in .{} files",
+            media_ext
+        ));
+    }
+    match media_ext.as_str() {
+        // The image goes first so `-disposition:0` marks it as the cover;
+        // `0:V` skips any cover the file already had.
+        "mp4" | "m4a" | "m4v" | "mov" | "mp3" => {
+            args.extend(
+                [
+                    "-i",
+                    &media_arg,
+                    "-i",
+                    &image_arg,
+                    "-map",
+                    "1",
+                    "-map",
+                    "0:V?",
+                    "-map",
+                    "0:a?",
+                    "-map",
+                    "0:s?",
+                    "-c",
+                    "copy",
+                    "-disposition:0",
+                    "attached_pic",
+                ]
+                .map(str::to_string),
+            );
+            if media_ext == "mp3" {
+                args.push("-id3v2_version".to_string());
+                args.push("3".to_string());
+            }
+        }
+        _ => {
+            let file_name = format!("cover.{}", image_ext);
+            args.extend(
+                [
+                    "-i",
+                    &media_arg,
+                    "-map",
+                    "0",
+                    "-c",
+                    "copy",
+                    "-attach",
+                    &image_arg,
+                    "-metadata:s:t",
+                    &format!("mimetype={}", mime_type),
+                    "-metadata:s:t",
+                    &format!("filename={}", file_name),
+                ]
+                .map(str::to_string),
+            );
+        }
+    }
+
+    let temp_output = media.with_extension(format!("cover.{}", media_ext));
+    args.push(temp_output.to_string_lossy().to_string());
+
+    let result = app
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| e.to_string())?
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !result.status.success() {
+        let _ = std::fs::remove_file(&temp_output);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let reason = stderr
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("unknown error");
+        return Err(format!(
+            "ffmpeg failed to embed the thumbnail: {}",
+            reason.trim()
+        ));
+    }
+
+    std::fs::rename(&temp_output, media).map_err(|e| {
+        let _ = std::fs::remove_file(&temp_output);
+        format!(
+            "Failed to replace {:?} with the re-tagged file: {}",
+            media, e
+        )
+    })
+}
+
+/// Merges already-downloaded video and audio parts (e.g. `.f137.mp4.part` and
+/// `.f140.m4a.part` left in `_dlpgui_temp`) into `output` with stream copy,
+/// for downloads that died at the merge step.
+#[tauri::command]
+pub async fn remux_parts(
+    app: AppHandle,
+    video_part: String,
+    audio_part: String,
+    output: String,
+) -> Result<String, String> {
+    for part in [&video_part, &audio_part] {
+        if !Path::new(part).is_file() {
+            return Err(format!("Part file {:?} does not exist", part));
+        }
+    }
+
+    let output_path = PathBuf::from(&output);
+    if output_path.exists() {
+        return Err(format!("Output file {:?} already exists", output));
+    }
+    if output_path.extension().is_none() {
+        return Err("Output file needs an extension such as .mp4 or .mkv".to_string());
+    }
+
+    let result = app
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| e.to_string())?
+        .args([
+            "-hide_banner",
+            "-n",
+            "-i",
+            &video_part,
+            "-i",
+            &audio_part,
+            "-map",
+            "0:v:0",
+            "-map",
+            "1:a:0",
+            "-c",
+            "copy",
+            &output,
+        ])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !result.status.success() {
+        let _ = std::fs::remove_file(&output_path);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let reason = stderr
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("unknown error");
+        return Err(format!(
+            "ffmpeg failed to merge the parts: {}",
+            reason.trim()
+        ));
+    }
+
+    Ok(output)
+}
+
+/// Default `remux_with_offset` output: the video's name without yt-dlp's
+/// `.f<format id>` part, as `.resynced.mkv` since mkv takes any codec pair.
+fn resynced_output_path(video: &Path) -> PathBuf {
+    let stem = video
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let format_suffix = Regex::new(r"\.f[\w-]+$").unwrap();
+    let stem = format_suffix.replace(&stem, "");
+    video.with_file_name(format!("{}.resynced.mkv", stem))
+}
+
+/// Re-merges streams kept with `keep_streams`, delaying the audio by
+/// `offset_ms` (negative plays it earlier) through ffmpeg's `-itsoffset`.
+/// Stream copy, so it takes seconds instead of a full redownload.
+#[tauri::command]
+pub async fn remux_with_offset(
+    app: AppHandle,
+    video: String,
+    audio: String,
+    offset_ms: i64,
+    output: Option<String>,
+) -> Result<String, String> {
+    for stream in [&video, &audio] {
+        if !Path::new(stream).is_file() {
+            return Err(format!("Stream file {:?} does not exist", stream));
+        }
+    }
+
+    let output_path = output
+        .map(|output| PathBuf::from(output.trim()))
+        .unwrap_or_else(|| resynced_output_path(Path::new(&video)));
+    if output_path.exists() {
+        return Err(format!("Output file {:?} already exists", output_path));
+    }
+    if output_path.extension().is_none() {
+        return Err("Output file needs an extension such as .mp4 or .mkv".to_string());
+    }
+    let output = output_path.to_string_lossy().to_string();
+    let offset = format!("{:.3}", offset_ms as f64 / 1000.0);
+
+    let result = app
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| e.to_string())?
+        .args([
+            "-hide_banner",
+            "-n",
+            "-i",
+            &video,
+            "-itsoffset",
+            &offset,
+            "-i",
+            &audio,
+            "-map",
+            "0:v:0",
+            "-map",
+            "1:a:0",
+            "-c",
+            "copy",
+            &output,
+        ])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if !result.status.success() {
+        let _ = std::fs::remove_file(&output_path);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let reason = stderr
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("unknown error");
+        return Err(format!(
+            "ffmpeg failed to re-merge the streams: {}",
+            reason.trim()
+        ));
+    }
+
+    Ok(output)
+}
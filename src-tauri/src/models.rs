@@ -1,5 +1,10 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
+use crate::errors::CommandError;
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct ExtensionDownloadRequest {
     pub request_id: String,
@@ -30,6 +35,39 @@ pub struct DownloadProgress {
     pub size: String,
     pub status: String,
     pub phase: String,
+    pub downloaded_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+    pub speed_bytes_per_sec: Option<u64>,
+    /// Live downloads have no known total, so `percentage` means nothing.
+    pub indeterminate: bool,
+    /// Recorded so far on live downloads, "HH:MM:SS".
+    pub live_duration: Option<String>,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct DownloadStats {
+    pub total_bytes: u64,
+    pub downloaded_bytes: u64,
+    pub speed_bytes_per_sec: u64,
+    /// Size of the streams (video, then audio) already finished, so the
+    /// totals keep growing instead of restarting with each stream.
+    pub finished_bytes: u64,
+    pub stream_total_bytes: u64,
+    pub stream_downloaded_bytes: u64,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct AggregateEta {
+    pub active_downloads: usize,
+    pub queued_downloads: usize,
+    pub remaining_bytes: u64,
+    pub remaining_size_formatted: String,
+    pub speed_bytes_per_sec: u64,
+    /// `None` while a running download hasn't reported its size yet.
+    pub eta_seconds: Option<u64>,
+    pub eta: String,
+    /// Some queued jobs have no known size, so draining takes at least `eta`.
+    pub eta_is_lower_bound: bool,
 }
 
 #[derive(Clone, Serialize, Debug)]
@@ -43,6 +81,61 @@ pub struct QualityOption {
     pub format_string: String,
     pub has_combined_audio: bool,
     pub available: bool,
+    pub video_size_estimated: bool,
+    pub audio_size_estimated: bool,
+    pub total_size_estimated: bool,
+    /// "video + audio" sizes for qualities that need a separate audio stream.
+    pub size_breakdown: Option<String>,
+    pub fps: Option<f64>,
+    /// Codec family of the picked video stream, e.g. "avc1" or "vp09".
+    pub vcodec: Option<String>,
+    /// Height `format_string` actually downloads. An unavailable bucket
+    /// borrows the nearest available height's format, so this differs from
+    /// `height` there; `None` when nothing is known to be available.
+    pub delivered_height: Option<i32>,
+}
+
+/// Start of one subtitle track, from `preview_subtitles`.
+#[derive(Clone, Serialize, Debug)]
+pub struct SubtitlePreview {
+    pub language: String,
+    /// Auto-generated captions rather than the uploader's subtitles.
+    pub automatic: bool,
+    /// "vtt", "srt", ... as delivered by the site.
+    pub format: String,
+    pub lines: Vec<String>,
+    /// More lines follow the ones returned.
+    pub truncated: bool,
+}
+
+/// One entry of a video's `chapters`, in seconds from the start.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Chapter {
+    pub title: String,
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+/// yt-dlp's own size for one format selection, from `get_exact_size`.
+#[derive(Clone, Serialize, Debug)]
+pub struct ExactSize {
+    /// `None` when neither the site nor yt-dlp's bitrate math gives a size.
+    pub size: Option<u64>,
+    /// False when any part of it is yt-dlp's `filesize_approx`.
+    pub exact: bool,
+    pub size_formatted: String,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct AudioQualityOption {
+    pub format_id: String,
+    pub quality: String,
+    pub codec: String,
+    pub ext: String,
+    pub bitrate: f64,
+    pub size: u64,
+    pub size_formatted: String,
+    pub language: Option<String>,
 }
 
 #[derive(Clone, Serialize, Debug)]
@@ -50,6 +143,26 @@ pub struct FormatsResponse {
     pub qualities: Vec<QualityOption>,
     pub best_audio_size: u64,
     pub best_audio_format_id: String,
+    pub duration_known: bool,
+    pub language: Option<String>,
+    /// yt-dlp `WARNING:` lines, e.g. formats withheld by SABR or a missing PO token.
+    pub warnings: Vec<String>,
+    /// Seek-preview thumbnail grids, kept apart from the real qualities.
+    pub storyboards: Vec<StoryboardOption>,
+}
+
+/// One storyboard format (`sb0`, `sb1`, ...): `fragment_count` images, each a
+/// `columns` x `rows` grid of `width` x `height` thumbnails.
+#[derive(Clone, Serialize, Debug)]
+pub struct StoryboardOption {
+    pub format_id: String,
+    pub width: u32,
+    pub height: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub fragment_count: usize,
+    /// Thumbnails per second of video.
+    pub fps: Option<f64>,
 }
 
 #[derive(Clone, Serialize, Debug)]
@@ -58,15 +171,70 @@ pub struct PlaylistVideo {
     pub title: String,
     pub url: String,
     pub duration: Option<f64>,
+    pub thumbnail: Option<String>,
+    pub uploader: Option<String>,
+    pub view_count: Option<u64>,
+    /// "public", "unlisted", "private", "removed", "members_only", ... when
+    /// the flat listing says; private and removed videos stay in the list.
+    pub availability: Option<String>,
+    /// Linked as a `/shorts/` URL; the duration isn't used to guess.
+    pub is_short: bool,
+}
+
+/// Which entries of a channel listing to keep.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistContentType {
+    #[default]
+    All,
+    VideosOnly,
+    ShortsOnly,
+}
+
+impl PlaylistContentType {
+    pub fn keeps(self, is_short: bool) -> bool {
+        match self {
+            PlaylistContentType::All => true,
+            PlaylistContentType::VideosOnly => !is_short,
+            PlaylistContentType::ShortsOnly => is_short,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct PlaylistFetchProgress {
+    pub url: String,
+    pub stage: String,
+    pub elapsed_seconds: u64,
+    pub pages_fetched: Option<u32>,
+    pub bytes_received: usize,
 }
 
 #[derive(Clone, Serialize, Debug)]
 pub struct PlaylistInfo {
     pub title: String,
     pub video_count: usize,
+    pub total_count: Option<usize>,
     pub channel: String,
     pub description: String,
     pub entries: Vec<PlaylistVideo>,
+    /// Entries without even an id, which can't be listed at all.
+    pub unlisted_entries: usize,
+    /// Entries dropped by the `content_type` filter.
+    pub filtered_entries: usize,
+}
+
+/// A slice of a playlist from `fetch_playlist_page`.
+#[derive(Clone, Serialize, Debug)]
+pub struct PlaylistPage {
+    /// 0-based index of the page's first entry in the playlist.
+    pub offset: usize,
+    pub entries: Vec<PlaylistVideo>,
+    /// Size of the whole playlist, when the site reports it.
+    pub total_count: Option<usize>,
+    pub has_more: bool,
+    pub unlisted_entries: usize,
+    pub filtered_entries: usize,
 }
 
 #[derive(Clone, Serialize, Debug)]
@@ -75,3 +243,586 @@ pub struct YtDlpVersionInfo {
     pub latest_version: String,
     pub update_available: bool,
 }
+
+/// Latest yt-dlp release as of the last check, so startup checks don't hit
+/// the GitHub API on every launch.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct YtDlpUpdateCheck {
+    /// Unix timestamp in seconds.
+    pub checked_at: u64,
+    pub latest_version: String,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct YtDlpUpdateProgress {
+    pub downloaded_bytes: u64,
+    /// Unknown when the server sends no Content-Length.
+    pub total_bytes: Option<u64>,
+    pub percent: Option<f64>,
+    /// True when the download continued a partial file from an earlier attempt.
+    pub resumed: bool,
+}
+
+/// Whether the configured JS runtime can be run, plus any other supported
+/// runtimes found on PATH as alternatives.
+#[derive(Clone, Serialize, Debug)]
+pub struct JsRuntimeStatus {
+    pub runtime: String,
+    pub available: bool,
+    pub version: Option<String>,
+    pub alternatives: Vec<String>,
+}
+
+/// Result of `diagnose_youtube`'s test extraction.
+#[derive(Clone, Serialize, Debug)]
+pub struct YoutubeDiagnostics {
+    pub extraction_ok: bool,
+    pub error: Option<CommandError>,
+    /// The configured JS runtime, probed the same way as at startup.
+    pub js_runtime: JsRuntimeStatus,
+    /// JS runtimes yt-dlp itself found, from its `-v` header.
+    pub js_runtimes: Option<String>,
+    /// Whether the `ejs` challenge solver loaded; `None` if yt-dlp didn't say.
+    pub remote_components_ok: Option<bool>,
+    pub round_trip_ms: u64,
+    pub warnings: Vec<String>,
+}
+
+/// Versions of the app and the tools it drives. A `None` tool version means
+/// the tool could not be run.
+#[derive(Clone, Serialize, Debug)]
+pub struct ToolVersions {
+    pub app_version: String,
+    pub ytdlp_version: Option<String>,
+    pub ffmpeg_version: Option<String>,
+    pub ffmpeg_path: Option<String>,
+    pub aria2c_version: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct AppSettings {
+    pub default_extra_args: Vec<String>,
+    /// Maps a download category ("audio", "video", ...) to a subfolder of the download dir.
+    pub category_folders: HashMap<String, String>,
+    /// Scratch directory for in-progress files; defaults to `_dlpgui_temp` in the download dir.
+    pub temp_dir: Option<String>,
+    /// Base folder for audio-only downloads started in the default download dir.
+    pub audio_download_dir: Option<String>,
+    /// Base folder for video downloads started in the default download dir.
+    pub video_download_dir: Option<String>,
+    /// Passes `--restrict-filenames`: ASCII-only names without spaces.
+    pub restrict_filenames: bool,
+    /// Passes `--windows-filenames`: strips characters Windows can't store.
+    pub windows_filenames: bool,
+    /// Which time finished files get as their modification time.
+    pub file_timestamp: FileTimestamp,
+    /// Used when a fetch or download doesn't pick an IP version itself.
+    pub ip_version: IpVersion,
+    pub output_template_preset: OutputTemplatePreset,
+    /// Used when `output_template_preset` is `custom`; relative to the download dir.
+    pub custom_output_template: Option<String>,
+    /// Last quality picked per host (see `url_host`), updated by `start_download`;
+    /// `update_settings` keeps the stored map.
+    pub host_qualities: HashMap<String, QualityPreference>,
+    /// Used for hosts without a remembered quality.
+    pub default_quality: Option<QualityPreference>,
+    /// Hard cap on video height; higher qualities are marked unavailable and
+    /// downloads are clamped to it.
+    pub max_height: Option<u32>,
+    /// How many queued downloads run at once; defaults to 2.
+    pub max_concurrent_downloads: Option<usize>,
+    /// A system yt-dlp to run instead of the bundled one; the bundled one is
+    /// used while this is unset or not a file. `update_ytdlp` only updates
+    /// the bundled one.
+    pub custom_ytdlp_path: Option<String>,
+    /// `--js-runtimes` value ("node", "deno", "bun", optionally "name:path");
+    /// defaults to node.
+    pub js_runtime: Option<String>,
+    /// `--cache-dir` for yt-dlp, where downloaded remote components (the `ejs`
+    /// challenge solver) are kept between runs; defaults to the app cache dir.
+    pub ytdlp_cache_dir: Option<String>,
+    /// Restart a download once with another player client when its speed
+    /// stays below `throttle_min_speed_kbps` for `throttle_grace_secs`.
+    pub throttle_recovery: bool,
+    /// Defaults to 100 KiB/s.
+    pub throttle_min_speed_kbps: Option<u64>,
+    /// Defaults to 30 seconds.
+    pub throttle_grace_secs: Option<u64>,
+    /// aria2c `-x` (connections per server), 1-16; defaults to 16.
+    pub aria2c_connections: Option<u8>,
+    /// aria2c `-s` (pieces downloaded in parallel), 1-16; defaults to 16.
+    pub aria2c_split: Option<u8>,
+    /// "error", "warn", "info", "debug" or "trace"; defaults to info.
+    pub log_level: Option<String>,
+    /// The log starts a new file each day; this many are kept, defaults to 3.
+    pub log_max_files: Option<usize>,
+    /// Look for a newer yt-dlp (at most once a day) when the app starts and
+    /// emit `ytdlp-update-available`; never installs it. Defaults to on.
+    pub check_ytdlp_update_on_startup: Option<bool>,
+    /// Time-of-day `--limit-rate` windows; the first one covering the local
+    /// time when a download starts or resumes applies, none means unthrottled.
+    /// A `--limit-rate` in the extra args takes precedence.
+    pub rate_limit_schedule: Vec<RateLimitWindow>,
+}
+
+/// Stream preferences used when picking the format behind each quality.
+/// Each one falls back to any stream when nothing matches.
+#[derive(Clone, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct FormatPreferences {
+    /// "av1", "vp9", "h264", "h265" or a raw vcodec prefix.
+    pub video_codec: Option<String>,
+    /// "opus", "aac" or a raw acodec prefix.
+    pub audio_codec: Option<String>,
+    pub max_fps: Option<u32>,
+    /// Pick formats that already contain audio where a height has one, so
+    /// the download needs no merge (and no ffmpeg for it).
+    pub prefer_progressive: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct QualityPreference {
+    /// "1080p", "audio", ... when it can be derived from the format string.
+    pub quality: Option<String>,
+    pub format_string: String,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileTimestamp {
+    /// `--mtime`: the Last-Modified time of the media, roughly the upload date.
+    #[default]
+    UploadDate,
+    /// `--no-mtime`: when the download finished, so "date modified" sorts by download.
+    DownloadTime,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputTemplatePreset {
+    #[default]
+    Flat,
+    ByChannel,
+    ByDate,
+    ByChannelThenDate,
+    Custom,
+}
+
+impl OutputTemplatePreset {
+    /// The yt-dlp `-o` template for the preset; `None` for `Custom`.
+    pub fn template(self) -> Option<&'static str> {
+        match self {
+            OutputTemplatePreset::Flat => Some("%(title)s.%(ext)s"),
+            OutputTemplatePreset::ByChannel => {
+                Some("%(channel,uploader|Unknown channel)s/%(title)s.%(ext)s")
+            }
+            OutputTemplatePreset::ByDate => {
+                Some("%(upload_date>%Y)s/%(upload_date>%Y-%m)s/%(title)s.%(ext)s")
+            }
+            OutputTemplatePreset::ByChannelThenDate => Some(
+                "%(channel,uploader|Unknown channel)s/%(upload_date>%Y)s/%(upload_date>%Y-%m)s/%(title)s.%(ext)s",
+            ),
+            OutputTemplatePreset::Custom => None,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct AuthCredentials {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub twofactor: Option<String>,
+    /// Value for `--cookies-from-browser`, e.g. "chrome" or "chrome:Profile 2".
+    pub cookies_from_browser: Option<String>,
+}
+
+impl std::fmt::Debug for AuthCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redact = |value: &Option<String>| value.as_ref().map(|_| "***");
+        f.debug_struct("AuthCredentials")
+            .field("username", &redact(&self.username))
+            .field("password", &redact(&self.password))
+            .field("twofactor", &redact(&self.twofactor))
+            .field("cookies_from_browser", &self.cookies_from_browser)
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleMode {
+    #[default]
+    English,
+    /// Prefer the video's spoken language (from `FormatsResponse::language`), plus English.
+    Auto,
+}
+
+/// Which IP family yt-dlp connects over; forcing IPv4 gets past throttling
+/// on some networks.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IpVersion {
+    #[default]
+    Auto,
+    V4,
+    V6,
+}
+
+impl IpVersion {
+    pub fn as_arg(self) -> Option<&'static str> {
+        match self {
+            IpVersion::Auto => None,
+            IpVersion::V4 => Some("--force-ipv4"),
+            IpVersion::V6 => Some("--force-ipv6"),
+        }
+    }
+}
+
+/// YouTube `player_client` values; alternate clients often get past age
+/// gates and bot checks.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerClient {
+    /// Leave client selection to yt-dlp.
+    #[default]
+    Default,
+    Web,
+    WebSafari,
+    WebEmbedded,
+    Mweb,
+    Tv,
+    TvEmbedded,
+    Ios,
+    Android,
+}
+
+impl PlayerClient {
+    /// Client to switch to when a download through `self` gets throttled.
+    pub fn throttle_fallback(self) -> PlayerClient {
+        match self {
+            PlayerClient::Tv => PlayerClient::Mweb,
+            _ => PlayerClient::Tv,
+        }
+    }
+
+    pub fn as_arg(self) -> Option<&'static str> {
+        match self {
+            PlayerClient::Default => None,
+            PlayerClient::Web => Some("web"),
+            PlayerClient::WebSafari => Some("web_safari"),
+            PlayerClient::WebEmbedded => Some("web_embedded"),
+            PlayerClient::Mweb => Some("mweb"),
+            PlayerClient::Tv => Some("tv"),
+            PlayerClient::TvEmbedded => Some("tv_embedded"),
+            PlayerClient::Ios => Some("ios"),
+            PlayerClient::Android => Some("android"),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct DownloadOptions {
+    pub extra_args: Vec<String>,
+    pub organize_by_category: bool,
+    pub category: Option<String>,
+    /// Passed to `--wait-for-video` as "MIN" or "MIN-MAX" seconds for scheduled premieres.
+    pub wait_for_video: Option<String>,
+    /// Exact audio format picked from `fetch_audio_formats`; implies an audio-only
+    /// download unless `video_format_id` is also set.
+    pub audio_format_id: Option<String>,
+    /// Exact video format id; downloaded as `-f VID+AID` (or just `VID`) without
+    /// any quality or height rewriting.
+    pub video_format_id: Option<String>,
+    pub fragment_retries: Option<u32>,
+    /// `Some(false)` aborts on the first missing fragment instead of yt-dlp's default skip.
+    pub skip_unavailable_fragments: Option<bool>,
+    pub write_description: bool,
+    /// Slow for popular videos; comments are stored in the info.json sidecar.
+    pub write_comments: bool,
+    /// Never written out, so exported queues don't leak credentials.
+    #[serde(skip_serializing)]
+    pub auth: Option<AuthCredentials>,
+    /// Also keep a standalone audio file extracted from the merged video.
+    pub keep_audio_copy: bool,
+    /// `--audio-format` for the audio copy; "best" avoids re-encoding when possible.
+    pub audio_copy_format: Option<String>,
+    /// Keep the separately downloaded video and audio streams (`--keep-video`)
+    /// next to the merged file, so `remux_with_offset` can re-merge them when
+    /// the audio turns out to be out of sync.
+    pub keep_streams: bool,
+    /// Container passed to `--merge-output-format`; defaults to mp4.
+    pub merge_output_format: Option<String>,
+    /// Attach the full info.json to the file; mkv only.
+    pub embed_info_json: bool,
+    pub subtitle_mode: SubtitleMode,
+    /// Subtitle languages to fetch and embed as separate tracks, e.g. ["en", "de"];
+    /// overrides `subtitle_mode` when not empty.
+    pub subtitle_languages: Vec<String>,
+    pub video_language: Option<String>,
+    pub player_client: PlayerClient,
+    /// Probe the finished file with ffmpeg and fix an extension that doesn't
+    /// match the real container.
+    pub verify_output: bool,
+    /// Save the live chat replay as a separate `.live_chat.json` file.
+    pub download_live_chat: bool,
+    /// Times to restart the download after a retryable failure before giving up.
+    pub auto_retries: Option<u32>,
+    /// Select `b[height<=N]` before `bv+ba` so a single pre-merged file is
+    /// downloaded whenever one exists.
+    pub prefer_progressive: bool,
+    /// Local jpg/png embedded as cover art after the download, replacing the
+    /// video's own thumbnail.
+    pub custom_thumbnail: Option<String>,
+    /// Start even when the video looks like it was already downloaded.
+    pub allow_duplicate: bool,
+    /// Record a live stream from its beginning instead of from now.
+    pub live_from_start: bool,
+    /// Extra ffmpeg options for the postprocessors, e.g. "-c:v libx264 -crf 20"
+    /// or "Merger+ffmpeg_o:-movflags +faststart"; passed as `--postprocessor-args`,
+    /// under `ffmpeg:` when no prefix is given.
+    pub postprocessor_args: Option<String>,
+    /// Overrides the `ip_version` setting for this download.
+    pub ip_version: Option<IpVersion>,
+    /// `--download-archive` file; playlist downloads set it so a resumed
+    /// playlist skips videos that finished before the journal caught up.
+    pub download_archive: Option<String>,
+    /// `--http-chunk-size` for the native downloader, e.g. "10M"; requesting
+    /// in chunks gets past per-connection speed caps. Ignored with aria2c.
+    pub http_chunk_size: Option<String>,
+    /// Only these chapters (from `fetch_chapters`) are downloaded, through
+    /// `--download-sections`; empty downloads the whole video.
+    pub chapters: Vec<Chapter>,
+    /// Mark SponsorBlock segments as chapters and embed them
+    /// (`--sponsorblock-mark all --embed-chapters`), so videos without
+    /// creator chapters still get navigable markers. Nothing is cut.
+    pub sponsorblock_chapters: bool,
+}
+
+/// Everything `start_download` needs to run one download.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DownloadJob {
+    pub id: String,
+    pub url: String,
+    pub download_dir: String,
+    pub format_string: String,
+    pub subtitles: bool,
+    pub use_aria2c: bool,
+    pub options: DownloadOptions,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PlaylistDownloadEntry {
+    /// Download id used for this video's progress and status events.
+    pub id: String,
+    pub url: String,
+    /// From `PlaylistVideo`; lets the `content_type` filter classify
+    /// entries whose download URL doesn't say whether they are Shorts.
+    #[serde(default)]
+    pub is_short: bool,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct PlaylistDownloadStatus {
+    pub playlist_id: String,
+    pub status: String,
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// The `.m3u8` written for the finished videos, when one was requested.
+    pub playlist_file: Option<String>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct UrlImportError {
+    pub line: usize,
+    pub content: String,
+    pub error: String,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct UrlImportResult {
+    pub urls: Vec<String>,
+    pub errors: Vec<UrlImportError>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct DownloadsSizeInfo {
+    pub total_bytes: u64,
+    pub file_count: u64,
+    pub skipped_entries: u64,
+    pub truncated: bool,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct DownloadMethodBenchmark {
+    pub method: String,
+    pub average_speed_bytes_per_sec: Option<u64>,
+    pub speed_formatted: String,
+    pub samples: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct DownloadBenchmark {
+    pub results: Vec<DownloadMethodBenchmark>,
+    pub recommended: Option<String>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct BrowserProfile {
+    /// Suffix for `--cookies-from-browser BROWSER:NAME`.
+    pub name: String,
+    pub display_name: String,
+    pub path: String,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ExtractorArgOption {
+    pub name: String,
+    pub description: String,
+    pub example: String,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ExtractorArgsHelp {
+    pub extractor: String,
+    pub args: Vec<ExtractorArgOption>,
+    pub documentation_url: String,
+}
+
+/// Media URL(s) behind one format. They are usually short-lived and bound to
+/// the IP that resolved them, so they may fail on another machine or later.
+#[derive(Clone, Serialize, Debug)]
+pub struct DirectUrl {
+    /// One URL per stream; a "VID+AID" selection yields two.
+    pub urls: Vec<String>,
+    /// Unix timestamp from the URL's `expire` parameter, when it has one.
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct StreamInfo {
+    pub id: String,
+    /// File being written while it downloads; safe to hand to mpv/VLC.
+    pub path: String,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct FormatsBatchEntry {
+    pub formats: Option<FormatsResponse>,
+    pub error: Option<CommandError>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct FormatsBatchProgress {
+    pub url: String,
+    pub success: bool,
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct LaunchRequest {
+    pub url: String,
+    pub auto_start: bool,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct QueuedDownload {
+    pub id: String,
+    pub url: String,
+}
+
+/// One entry of `AppSettings::rate_limit_schedule`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RateLimitWindow {
+    /// Local "HH:MM"; a window ending before it starts runs past midnight.
+    pub start: String,
+    pub end: String,
+    /// `--limit-rate` value such as "2M"; missing means unthrottled.
+    #[serde(default)]
+    pub limit: Option<String>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct QueueState {
+    pub paused: bool,
+    pub max_concurrent: usize,
+    /// `--limit-rate` a download starting now gets from the schedule.
+    pub rate_limit: Option<String>,
+    pub running: Vec<String>,
+    pub queued: Vec<QueuedDownload>,
+}
+
+/// A playlist download that hasn't finished every entry, kept so
+/// `resume_playlist` can continue it with the same settings. Credentials in
+/// `options` are not persisted.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PlaylistJournalEntry {
+    pub playlist_id: String,
+    pub entries: Vec<PlaylistDownloadEntry>,
+    pub completed_ids: Vec<String>,
+    /// Entries skipped as duplicates or cancelled on their own.
+    pub skipped_ids: Vec<String>,
+    pub download_dir: String,
+    pub format_string: String,
+    pub subtitles: bool,
+    pub use_aria2c: bool,
+    pub options: DownloadOptions,
+    pub write_playlist_file: bool,
+    pub playlist_title: Option<String>,
+    /// Finished files in playlist order, for the `.m3u8`.
+    pub downloaded_paths: Vec<PathBuf>,
+}
+
+/// A cancelled download whose partial files were kept for later.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ResumableDownload {
+    pub id: String,
+    pub url: String,
+    pub download_dir: String,
+    pub format_string: String,
+    pub subtitles: bool,
+    pub use_aria2c: bool,
+    /// Folder holding the `.part` files.
+    pub temp_dir: String,
+    /// Unix timestamp in seconds.
+    pub cancelled_at: u64,
+}
+
+/// A finished download, used to flag later downloads of the same video.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DownloadedVideo {
+    pub video_id: Option<String>,
+    pub title: Option<String>,
+    pub duration: Option<f64>,
+    pub path: String,
+    /// Unix timestamp in seconds.
+    pub downloaded_at: u64,
+}
+
+/// File written by `export_state`. `history` is the frontend's download
+/// history, kept as opaque JSON.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ExportedState {
+    pub version: u32,
+    /// Unix timestamp in seconds.
+    pub exported_at: u64,
+    #[serde(default)]
+    pub history: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub queue: Vec<DownloadJob>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ImportedState {
+    pub history: Vec<serde_json::Value>,
+    /// Jobs added to the (now paused) backend queue.
+    pub queued: usize,
+    /// Jobs skipped because their id was already queued or running.
+    pub skipped: usize,
+}
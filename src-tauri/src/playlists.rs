@@ -0,0 +1,594 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_shell::process::CommandEvent;
+
+use crate::args::redact_args;
+use crate::downloads::{DownloadOutcome, spawn_download};
+use crate::duplicates::find_possible_duplicate;
+use crate::journal::{
+    find_playlist_journal, playlist_archive_path, record_playlist_progress, remove_playlist_journal,
+};
+use crate::models::{
+    DownloadJob, DownloadOptions, PlaylistContentType, PlaylistDownloadEntry,
+    PlaylistDownloadStatus, PlaylistFetchProgress, PlaylistInfo, PlaylistJournalEntry,
+    PlaylistPage, PlaylistVideo,
+};
+use crate::process::{register_fetch, unregister_fetch};
+use crate::runtime::ytdlp_command;
+use crate::state::ACTIVE_PLAYLISTS;
+
+const PLAYLIST_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+fn emit_playlist_progress(
+    app: &AppHandle,
+    url: &str,
+    stage: &str,
+    started: Instant,
+    pages_fetched: Option<u32>,
+    bytes_received: usize,
+) {
+    let _ = app.emit(
+        "playlist-fetch-progress",
+        PlaylistFetchProgress {
+            url: url.to_string(),
+            stage: stage.to_string(),
+            elapsed_seconds: started.elapsed().as_secs(),
+            pages_fetched,
+            bytes_received,
+        },
+    );
+}
+
+fn entry_thumbnail(entry: &serde_json::Value) -> Option<String> {
+    if let Some(thumbnail) = entry["thumbnail"].as_str() {
+        return Some(thumbnail.to_string());
+    }
+
+    // Flat entries usually only carry a `thumbnails` list; pick the widest one.
+    entry["thumbnails"]
+        .as_array()?
+        .iter()
+        .filter_map(|thumbnail| {
+            let url = thumbnail["url"].as_str()?;
+            Some((thumbnail["width"].as_u64().unwrap_or(0), url))
+        })
+        .max_by_key(|(width, _)| *width)
+        .map(|(_, url)| url.to_string())
+}
+
+/// Flat entries mark unplayable videos with a placeholder title like
+/// "[Private video]" and often an `availability` field.
+fn entry_availability(entry: &serde_json::Value) -> Option<String> {
+    let from_title = match entry["title"].as_str() {
+        Some("[Private video]") => Some("private"),
+        Some("[Deleted video]") => Some("removed"),
+        Some("[Unavailable video]") => Some("unavailable"),
+        _ => None,
+    };
+    let availability = match entry["availability"].as_str() {
+        Some("subscriber_only") => Some("members_only"),
+        Some("needs_auth") if from_title.is_some() => from_title,
+        Some(availability) => Some(availability),
+        None => from_title,
+    };
+    availability.map(|availability| availability.to_string())
+}
+
+/// Flat channel listings link Shorts as `/shorts/ID`. Duration says nothing
+/// either way: Shorts can run for minutes and regular videos be seconds long.
+fn is_short(url: &str) -> bool {
+    url.contains("/shorts/")
+}
+
+fn parse_playlist_entry(entry: &serde_json::Value) -> Option<PlaylistVideo> {
+    let id = entry["id"].as_str()?.to_string();
+    let video_title = entry["title"]
+        .as_str()
+        .unwrap_or("Unknown Video")
+        .to_string();
+    let video_url = entry["url"]
+        .as_str()
+        .map(|url| url.to_string())
+        .unwrap_or_else(|| format!("https://www.youtube.com/watch?v={}", id));
+
+    let duration = entry["duration"].as_f64();
+    Some(PlaylistVideo {
+        is_short: is_short(&video_url)
+            || entry["webpage_url"].as_str().is_some_and(is_short),
+        id,
+        title: video_title,
+        url: video_url,
+        duration,
+        thumbnail: entry_thumbnail(entry),
+        uploader: entry["uploader"]
+            .as_str()
+            .or_else(|| entry["channel"].as_str())
+            .map(|uploader| uploader.to_string()),
+        view_count: entry["view_count"].as_u64(),
+        availability: entry_availability(entry),
+    })
+}
+
+/// Runs `yt-dlp -J --flat-playlist` on `url` with `extra_args`, emitting
+/// `playlist-fetch-progress` while the listing is enumerated.
+async fn fetch_flat_playlist_json(
+    app: &AppHandle,
+    url: &str,
+    extra_args: Vec<String>,
+    request_id: Option<&str>,
+) -> Result<serde_json::Value, String> {
+    let sidecar_command = ytdlp_command(app).map_err(|e| e.to_string())?;
+    let mut args = vec![
+        "-J".to_string(),
+        "--flat-playlist".to_string(),
+        "--no-warnings".to_string(),
+    ];
+    args.extend(extra_args);
+    args.push(url.to_string());
+    tracing::debug!("yt-dlp args: {:?}", redact_args(&args));
+
+    let (mut rx, child) = sidecar_command
+        .args(args)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(request_id) = request_id {
+        register_fetch(request_id, child);
+    }
+
+    // -J keeps yt-dlp quiet until the whole playlist is enumerated, so a
+    // heartbeat is what keeps the UI alive; page lines are reported when seen.
+    let re_page = Regex::new(r"Downloading (?:API JSON )?page (\d+)").unwrap();
+    let started = Instant::now();
+    let mut heartbeat = tokio::time::interval(PLAYLIST_HEARTBEAT_INTERVAL);
+    let mut pages_fetched = None;
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let mut exit_code = None;
+
+    loop {
+        tokio::select! {
+            event = rx.recv() => match event {
+                Some(CommandEvent::Stdout(line)) => stdout.extend(line),
+                Some(CommandEvent::Stderr(line)) => {
+                    let line_str = String::from_utf8_lossy(&line);
+                    if let Some(caps) = re_page.captures(&line_str) {
+                        pages_fetched = caps[1].parse::<u32>().ok();
+                        emit_playlist_progress(
+                            app,
+                            url,
+                            "enumerating",
+                            started,
+                            pages_fetched,
+                            stdout.len(),
+                        );
+                    }
+                    stderr.extend(line);
+                }
+                Some(CommandEvent::Terminated(payload)) => exit_code = payload.code,
+                Some(_) => {}
+                None => break,
+            },
+            _ = heartbeat.tick() => {
+                emit_playlist_progress(
+                    app,
+                    url,
+                    "enumerating",
+                    started,
+                    pages_fetched,
+                    stdout.len(),
+                );
+            }
+        }
+    }
+
+    if let Some(request_id) = request_id {
+        if !unregister_fetch(request_id) {
+            emit_playlist_progress(app, url, "cancelled", started, pages_fetched, stdout.len());
+            return Err("Playlist fetch cancelled".to_string());
+        }
+    }
+
+    if exit_code != Some(0) {
+        let stderr = String::from_utf8_lossy(&stderr);
+        emit_playlist_progress(app, url, "failed", started, pages_fetched, stdout.len());
+        return Err(format!("Failed to fetch playlist info: {}", stderr));
+    }
+
+    emit_playlist_progress(app, url, "parsing", started, pages_fetched, stdout.len());
+
+    let json_str = String::from_utf8_lossy(&stdout);
+    let json: serde_json::Value =
+        serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+
+    emit_playlist_progress(app, url, "done", started, pages_fetched, stdout.len());
+    Ok(json)
+}
+
+/// Listed entries of a flat playlist JSON kept by `content_type`, with how
+/// many had no id and how many the filter dropped.
+fn playlist_entries(
+    json: &serde_json::Value,
+    content_type: PlaylistContentType,
+) -> (Vec<PlaylistVideo>, usize, usize) {
+    let raw_entries = json["entries"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let entries: Vec<PlaylistVideo> = raw_entries
+        .iter()
+        .filter_map(parse_playlist_entry)
+        .collect();
+    let unlisted_entries = raw_entries.len() - entries.len();
+    let listed_entries = entries.len();
+    let entries: Vec<PlaylistVideo> = entries
+        .into_iter()
+        .filter(|entry| content_type.keeps(entry.is_short))
+        .collect();
+    let filtered_entries = listed_entries - entries.len();
+    (entries, unlisted_entries, filtered_entries)
+}
+
+#[tauri::command]
+pub async fn fetch_playlist_info(
+    app: AppHandle,
+    url: String,
+    limit: Option<usize>,
+    request_id: Option<String>,
+    content_type: Option<PlaylistContentType>,
+) -> Result<PlaylistInfo, String> {
+    let mut args = Vec::new();
+    if let Some(limit) = limit {
+        if limit == 0 {
+            return Err("Playlist limit must be at least 1".to_string());
+        }
+        args.push("--playlist-end".to_string());
+        args.push(limit.to_string());
+    }
+    let json = fetch_flat_playlist_json(&app, &url, args, request_id.as_deref()).await?;
+
+    let title = json["title"]
+        .as_str()
+        .unwrap_or("Unknown Playlist")
+        .to_string();
+    let channel = json["channel"]
+        .as_str()
+        .or_else(|| json["uploader"].as_str())
+        .unwrap_or("Unknown Channel")
+        .to_string();
+    let description = json["description"].as_str().unwrap_or("").to_string();
+
+    let (entries, unlisted_entries, filtered_entries) =
+        playlist_entries(&json, content_type.unwrap_or_default());
+
+    // yt-dlp reports the full size of the playlist even when --playlist-end
+    // truncated the entries, which lets the UI show "first N of M".
+    let total_count = json["playlist_count"]
+        .as_u64()
+        .or_else(|| json["n_entries"].as_u64())
+        .map(|count| count as usize)
+        .filter(|count| *count >= entries.len());
+
+    Ok(PlaylistInfo {
+        video_count: entries.len(),
+        total_count,
+        title,
+        channel,
+        description,
+        entries,
+        unlisted_entries,
+        filtered_entries,
+    })
+}
+
+/// One page of `url`'s entries, `limit` of them starting `offset` (0-based)
+/// into the playlist, so huge channels can be listed as the user scrolls
+/// instead of in one `fetch_playlist_info` payload.
+#[tauri::command]
+pub async fn fetch_playlist_page(
+    app: AppHandle,
+    url: String,
+    offset: usize,
+    limit: usize,
+    request_id: Option<String>,
+    content_type: Option<PlaylistContentType>,
+) -> Result<PlaylistPage, String> {
+    if limit == 0 {
+        return Err("Playlist page size must be at least 1".to_string());
+    }
+    // --playlist-items counts from 1 and includes both ends.
+    let args = vec![
+        "--playlist-items".to_string(),
+        format!("{}:{}", offset + 1, offset + limit),
+    ];
+    let json = fetch_flat_playlist_json(&app, &url, args, request_id.as_deref()).await?;
+
+    let (entries, unlisted_entries, filtered_entries) =
+        playlist_entries(&json, content_type.unwrap_or_default());
+
+    let total_count = json["playlist_count"]
+        .as_u64()
+        .or_else(|| json["n_entries"].as_u64())
+        .map(|count| count as usize);
+    let returned_entries = entries.len() + unlisted_entries + filtered_entries;
+    // Without a total, a full page is the only hint that more may follow.
+    let has_more = match total_count {
+        Some(total) => offset + returned_entries < total,
+        None => returned_entries == limit,
+    };
+
+    Ok(PlaylistPage {
+        offset,
+        entries,
+        total_count,
+        has_more,
+        unlisted_entries,
+        filtered_entries,
+    })
+}
+
+fn set_current_playlist_video(playlist_id: &str, video_id: Option<&str>) -> bool {
+    match ACTIVE_PLAYLISTS.lock() {
+        Ok(mut playlists) => match playlists.get_mut(playlist_id) {
+            Some(current) => {
+                *current = video_id.map(|id| id.to_string());
+                true
+            }
+            None => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// Writes `<title>.m3u8` into `dir` listing `paths` in order, relative to
+/// `dir` where possible so the folder can be moved as a whole.
+fn write_m3u(dir: &Path, title: &str, paths: &[PathBuf]) -> Result<PathBuf, String> {
+    let file_stem: String = title
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let file_stem = file_stem.trim().trim_end_matches('.');
+    let file_stem = if file_stem.is_empty() { "playlist" } else { file_stem };
+    let m3u_path = dir.join(format!("{}.m3u8", file_stem));
+
+    let mut contents = String::from("#EXTM3U\n");
+    for path in paths {
+        let entry = path.strip_prefix(dir).unwrap_or(path);
+        if let Some(name) = path.file_stem() {
+            contents.push_str(&format!("#EXTINF:-1,{}\n", name.to_string_lossy()));
+        }
+        contents.push_str(&entry.to_string_lossy());
+        contents.push('\n');
+    }
+    std::fs::write(&m3u_path, contents)
+        .map_err(|e| format!("Failed to write playlist file: {}", e))?;
+    Ok(m3u_path)
+}
+
+/// Downloads playlist entries one at a time. Cancelling an entry's id skips
+/// it and moves on; cancelling `playlist_id` stops the whole playlist.
+/// With `write_playlist_file`, the finished videos are listed in order in
+/// `<playlist_title>.m3u8` in `download_dir`; failed and skipped ones are left out.
+/// `content_type` drops Shorts or regular videos before anything starts.
+/// Progress is journaled, so an interrupted playlist can be finished with
+/// `resume_playlist`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_playlist_download(
+    app: AppHandle,
+    playlist_id: String,
+    entries: Vec<PlaylistDownloadEntry>,
+    download_dir: String,
+    format_string: String,
+    subtitles: bool,
+    use_aria2c: bool,
+    options: Option<DownloadOptions>,
+    write_playlist_file: Option<bool>,
+    playlist_title: Option<String>,
+    content_type: Option<PlaylistContentType>,
+) -> Result<(), String> {
+    let content_type = content_type.unwrap_or_default();
+    let entries: Vec<PlaylistDownloadEntry> = entries
+        .into_iter()
+        .filter(|entry| content_type.keeps(entry.is_short || is_short(&entry.url)))
+        .collect();
+    if entries.is_empty() {
+        return Err("Playlist has no entries to download".to_string());
+    }
+
+    run_playlist(
+        app,
+        PlaylistJournalEntry {
+            playlist_id,
+            entries,
+            completed_ids: Vec::new(),
+            skipped_ids: Vec::new(),
+            download_dir,
+            format_string,
+            subtitles,
+            use_aria2c,
+            options: options.unwrap_or_default(),
+            write_playlist_file: write_playlist_file.unwrap_or(false),
+            playlist_title,
+            downloaded_paths: Vec::new(),
+        },
+    )
+}
+
+/// Continues an interrupted playlist download: finished and skipped entries
+/// are left alone, failed ones are tried again, and a video stopped with
+/// `keep_partial` continues from its `.part` files. The first status event
+/// already counts the entries finished before.
+#[tauri::command]
+pub async fn resume_playlist(app: AppHandle, playlist_id: String) -> Result<(), String> {
+    let journal = find_playlist_journal(&app, &playlist_id)
+        .ok_or_else(|| format!("Playlist {} has nothing to resume", playlist_id))?;
+    run_playlist(app, journal)
+}
+
+fn run_playlist(app: AppHandle, mut journal: PlaylistJournalEntry) -> Result<(), String> {
+    let playlist_id = journal.playlist_id.clone();
+    {
+        let mut playlists = ACTIVE_PLAYLISTS.lock().map_err(|e| e.to_string())?;
+        if playlists.contains_key(&playlist_id) {
+            return Err(format!("Playlist {} is already downloading", playlist_id));
+        }
+        playlists.insert(playlist_id.clone(), None);
+    }
+
+    let mut options = journal.options.clone();
+    match playlist_archive_path(&app, &playlist_id) {
+        Ok(archive) => {
+            if let Some(parent) = archive.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            options.download_archive = Some(archive.to_string_lossy().to_string());
+        }
+        Err(err) => tracing::warn!("No download archive for playlist {}: {}", playlist_id, err),
+    }
+    record_playlist_progress(&app, &journal);
+
+    tokio::spawn(async move {
+        let mut status = PlaylistDownloadStatus {
+            playlist_id: playlist_id.clone(),
+            status: "downloading".to_string(),
+            total: journal.entries.len(),
+            completed: journal.completed_ids.len(),
+            failed: 0,
+            skipped: journal.skipped_ids.len(),
+            playlist_file: None,
+        };
+        let _ = app.emit("playlist-download-status", status.clone());
+
+        for entry in journal.entries.clone() {
+            if journal.completed_ids.contains(&entry.id) || journal.skipped_ids.contains(&entry.id)
+            {
+                continue;
+            }
+            if !set_current_playlist_video(&playlist_id, Some(&entry.id)) {
+                status.status = "cancelled".to_string();
+                break;
+            }
+
+            // Syncing a playlist again should only fetch what is missing.
+            let duplicate = (!options.allow_duplicate)
+                .then(|| find_possible_duplicate(&app, &entry.url, &journal.download_dir))
+                .flatten();
+            if let Some(existing_path) = duplicate {
+                status.skipped += 1;
+                journal.skipped_ids.push(entry.id.clone());
+                record_playlist_progress(&app, &journal);
+                let _ = app.emit(
+                    "download-status",
+                    serde_json::json!({
+                        "id": entry.id.clone(),
+                        "status": "skipped",
+                        "reason": "possible_duplicate",
+                        "existing_path": existing_path,
+                        "playlist_id": playlist_id.clone(),
+                    }),
+                );
+                let _ = app.emit("playlist-download-status", status.clone());
+                continue;
+            }
+
+            let job = DownloadJob {
+                id: entry.id.clone(),
+                url: entry.url,
+                download_dir: journal.download_dir.clone(),
+                format_string: journal.format_string.clone(),
+                subtitles: journal.subtitles,
+                use_aria2c: journal.use_aria2c,
+                options: options.clone(),
+            };
+            let outcome = match spawn_download(&app, job) {
+                Ok(handle) => match handle.await {
+                    Ok(result) => {
+                        if let Some(path) = result.final_path {
+                            journal.downloaded_paths.push(path);
+                        }
+                        result.outcome
+                    }
+                    Err(_) => DownloadOutcome::Failed,
+                },
+                Err(err) => {
+                    let _ = app.emit(
+                        "download-status",
+                        serde_json::json!({
+                            "id": entry.id.clone(),
+                            "status": "error",
+                            "error": err,
+                        }),
+                    );
+                    DownloadOutcome::Failed
+                }
+            };
+
+            match outcome {
+                DownloadOutcome::Completed => {
+                    status.completed += 1;
+                    journal.completed_ids.push(entry.id.clone());
+                }
+                DownloadOutcome::Failed => status.failed += 1,
+                DownloadOutcome::Cancelled => {
+                    if !set_current_playlist_video(&playlist_id, None) {
+                        // The whole playlist was cancelled, not just this video.
+                        status.status = "cancelled".to_string();
+                        break;
+                    }
+                    status.skipped += 1;
+                    journal.skipped_ids.push(entry.id.clone());
+                    let _ = app.emit(
+                        "download-status",
+                        serde_json::json!({
+                            "id": entry.id.clone(),
+                            "status": "skipped",
+                            "playlist_id": playlist_id.clone(),
+                        }),
+                    );
+                }
+            }
+            record_playlist_progress(&app, &journal);
+            let _ = app.emit("playlist-download-status", status.clone());
+        }
+
+        if let Ok(mut playlists) = ACTIVE_PLAYLISTS.lock() {
+            playlists.remove(&playlist_id);
+        }
+        if status.status != "cancelled" {
+            status.status = "completed".to_string();
+        }
+        // Kept while anything is left to do, so the playlist can be resumed.
+        if status.status == "completed" && status.failed == 0 {
+            remove_playlist_journal(&app, &playlist_id);
+        } else {
+            record_playlist_progress(&app, &journal);
+        }
+        if journal.write_playlist_file && !journal.downloaded_paths.is_empty() {
+            let title = journal.playlist_title.as_deref().unwrap_or(&playlist_id);
+            let download_dir = Path::new(&journal.download_dir);
+            match write_m3u(download_dir, title, &journal.downloaded_paths) {
+                Ok(path) => status.playlist_file = Some(path.to_string_lossy().to_string()),
+                Err(err) => {
+                    let _ = app.emit(
+                        "download-log",
+                        serde_json::json!({
+                            "id": playlist_id.clone(),
+                            "message": err,
+                            "is_error": true,
+                        }),
+                    );
+                }
+            }
+        }
+        let _ = app.emit("playlist-download-status", status);
+    });
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use tauri_plugin_shell::process::{Command, CommandChild, CommandEvent};
+
+use crate::state::ACTIVE_FETCHES;
+
+pub struct FetchOutput {
+    pub code: Option<i32>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// Stdout went past the limit of `run_fetch_limited`; it was dropped and
+    /// the child killed.
+    pub stdout_overflow: bool,
+}
+
+impl FetchOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Kills the child and, on Windows, everything it spawned (node, ffmpeg, aria2c).
+pub fn kill_process_tree(child: CommandChild) {
+    #[cfg(target_os = "windows")]
+    {
+        let pid = child.pid();
+        let output = std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .output();
+
+        match output {
+            Ok(result) => {
+                if !result.status.success() {
+                    let _ = child.kill();
+                }
+            }
+            Err(_) => {
+                let _ = child.kill();
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = child.kill();
+    }
+}
+
+pub fn register_fetch(request_id: &str, child: CommandChild) {
+    if let Ok(mut fetches) = ACTIVE_FETCHES.lock() {
+        fetches.insert(request_id.to_string(), child);
+    }
+}
+
+/// Returns false when the fetch was already removed by `cancel_fetch`.
+pub fn unregister_fetch(request_id: &str) -> bool {
+    ACTIVE_FETCHES
+        .lock()
+        .map(|mut fetches| fetches.remove(request_id).is_some())
+        .unwrap_or(false)
+}
+
+/// Runs a one-shot yt-dlp query, keeping its child cancellable under `request_id`.
+pub async fn run_fetch(command: Command, request_id: Option<&str>) -> Result<FetchOutput, String> {
+    run_fetch_limited(command, request_id, usize::MAX).await
+}
+
+/// Like `run_fetch`, but stops buffering once stdout passes `max_stdout`
+/// bytes: the child is killed and `stdout_overflow` set instead.
+pub async fn run_fetch_limited(
+    command: Command,
+    request_id: Option<&str>,
+    max_stdout: usize,
+) -> Result<FetchOutput, String> {
+    let (mut rx, child) = command.spawn().map_err(|e| e.to_string())?;
+    let mut unregistered_child = None;
+    match request_id {
+        Some(request_id) => register_fetch(request_id, child),
+        None => unregistered_child = Some(child),
+    }
+
+    let mut output = FetchOutput {
+        code: None,
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+        stdout_overflow: false,
+    };
+
+    while let Some(event) = rx.recv().await {
+        match event {
+            CommandEvent::Stdout(_) if output.stdout_overflow => {}
+            CommandEvent::Stdout(line) if output.stdout.len() + line.len() > max_stdout => {
+                output.stdout_overflow = true;
+                output.stdout = Vec::new();
+                let child = match request_id {
+                    Some(request_id) => ACTIVE_FETCHES
+                        .lock()
+                        .ok()
+                        .and_then(|mut fetches| fetches.remove(request_id)),
+                    None => unregistered_child.take(),
+                };
+                if let Some(child) = child {
+                    kill_process_tree(child);
+                }
+            }
+            CommandEvent::Stdout(line) => output.stdout.extend(line),
+            CommandEvent::Stderr(line) => output.stderr.extend(line),
+            CommandEvent::Terminated(payload) => output.code = payload.code,
+            _ => {}
+        }
+    }
+
+    if let Some(request_id) = request_id {
+        // An overflowing fetch took its own child out of the registry.
+        if !unregister_fetch(request_id) && !output.stdout_overflow {
+            return Err("Fetch cancelled".to_string());
+        }
+    }
+
+    Ok(output)
+}
+
+#[tauri::command]
+pub fn cancel_fetch(request_id: String) -> Result<bool, String> {
+    let child = {
+        let mut fetches = ACTIVE_FETCHES.lock().map_err(|e| e.to_string())?;
+        fetches.remove(&request_id)
+    };
+
+    match child {
+        Some(child) => {
+            kill_process_tree(child);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
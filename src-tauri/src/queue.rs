@@ -0,0 +1,226 @@
+use tauri::{AppHandle, Emitter};
+
+use crate::downloads::{check_duplicate, kill_download, spawn_download, validate_format_ids};
+use crate::errors::CommandError;
+use crate::models::{DownloadJob, DownloadOptions, QueueState, QueuedDownload};
+use crate::ratelimit::scheduled_rate_limit;
+use crate::settings::current_settings;
+use crate::state::{ACTIVE_DOWNLOADS, DOWNLOAD_QUEUE, PAUSED_DOWNLOADS};
+
+const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 2;
+
+fn max_concurrent_downloads() -> usize {
+    current_settings()
+        .max_concurrent_downloads
+        .filter(|max| *max > 0)
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_DOWNLOADS)
+}
+
+/// Starts queued jobs until the concurrency limit is reached or the queue is
+/// paused. Each started job calls back in here when it finishes.
+pub fn pump_queue(app: &AppHandle) {
+    let max_concurrent = max_concurrent_downloads();
+    loop {
+        let job = {
+            let Ok(mut queue) = DOWNLOAD_QUEUE.lock() else {
+                return;
+            };
+            if queue.paused || queue.running.len() >= max_concurrent {
+                return;
+            }
+            let Some(job) = queue.pending.pop_front() else {
+                return;
+            };
+            queue.running.insert(job.id.clone(), job.clone());
+            job
+        };
+        run_queued_job(app, job);
+    }
+}
+
+/// Spawns a job already moved into `running`, freeing its slot (and
+/// requeueing it if asked to) when it exits.
+fn run_queued_job(app: &AppHandle, job: DownloadJob) {
+    let id = job.id.clone();
+    match spawn_download(app, job) {
+        Ok(handle) => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let _ = handle.await;
+                let requeued_position = DOWNLOAD_QUEUE.lock().ok().and_then(|mut queue| {
+                    let job = queue.running.remove(&id)?;
+                    queue.requeued.remove(&id).then(|| {
+                        queue.pending.push_back(job);
+                        queue.pending.len()
+                    })
+                });
+                if let Some(position) = requeued_position {
+                    let _ = app.emit(
+                        "download-status",
+                        serde_json::json!({
+                            "id": id,
+                            "status": "queued",
+                            "position": position,
+                            "requeued": true,
+                        }),
+                    );
+                }
+                pump_queue(&app);
+            });
+        }
+        Err(err) => {
+            if let Ok(mut queue) = DOWNLOAD_QUEUE.lock() {
+                queue.running.remove(&id);
+            }
+            let _ = app.emit(
+                "download-status",
+                serde_json::json!({
+                    "id": id,
+                    "status": "error",
+                    "error": err,
+                }),
+            );
+        }
+    }
+}
+
+/// Drops `id` from the pending queue; returns whether it was queued.
+pub fn remove_queued(id: &str) -> bool {
+    let Ok(mut queue) = DOWNLOAD_QUEUE.lock() else {
+        return false;
+    };
+    let before = queue.pending.len();
+    queue.pending.retain(|job| job.id != id);
+    queue.pending.len() != before
+}
+
+/// Adds a download to the backend queue; it starts as soon as a slot is free
+/// and the queue isn't paused. Takes the same arguments as `start_download`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn enqueue_download(
+    app: AppHandle,
+    id: String,
+    url: String,
+    download_dir: String,
+    format_string: String,
+    subtitles: bool,
+    use_aria2c: bool,
+    options: Option<DownloadOptions>,
+) -> Result<(), CommandError> {
+    let options = options.unwrap_or_default();
+    check_duplicate(&app, &url, &download_dir, &options)?;
+    validate_format_ids(&app, &url, &options).await?;
+    // Started directly with `start_download`, or paused.
+    let started = ACTIVE_DOWNLOADS
+        .lock()
+        .map(|downloads| downloads.contains_key(&id))
+        .unwrap_or(false)
+        || PAUSED_DOWNLOADS
+            .lock()
+            .map(|paused_downloads| paused_downloads.contains_key(&id))
+            .unwrap_or(false);
+    if started {
+        return Err(format!("Download {} is already running", id).into());
+    }
+    let position = {
+        let mut queue = DOWNLOAD_QUEUE.lock().map_err(|e| e.to_string())?;
+        if queue.running.contains_key(&id) || queue.pending.iter().any(|job| job.id == id) {
+            return Err(format!("Download {} is already queued", id).into());
+        }
+        queue.pending.push_back(DownloadJob {
+            id: id.clone(),
+            url,
+            download_dir,
+            format_string,
+            subtitles,
+            use_aria2c,
+            options,
+        });
+        queue.pending.len()
+    };
+
+    let _ = app.emit(
+        "download-status",
+        serde_json::json!({
+            "id": id,
+            "status": "queued",
+            "position": position,
+        }),
+    );
+    pump_queue(&app);
+    Ok(())
+}
+
+/// Starts a queued download right away, even past the concurrency limit.
+/// New jobs still wait until the running count drops below the limit.
+#[tauri::command]
+pub fn force_start_download(app: AppHandle, id: String) -> Result<(), String> {
+    let job = {
+        let mut queue = DOWNLOAD_QUEUE.lock().map_err(|e| e.to_string())?;
+        let index = queue
+            .pending
+            .iter()
+            .position(|job| job.id == id)
+            .ok_or_else(|| format!("Download {} is not queued", id))?;
+        let job = queue.pending.remove(index).ok_or("Queued download disappeared")?;
+        queue.running.insert(job.id.clone(), job.clone());
+        job
+    };
+    run_queued_job(&app, job);
+    Ok(())
+}
+
+/// Stops a download started from the queue, keeping its partial files, and
+/// puts it back at the end of the queue to resume when its turn comes.
+#[tauri::command]
+pub fn requeue_download(app: AppHandle, id: String) -> Result<(), String> {
+    {
+        let mut queue = DOWNLOAD_QUEUE.lock().map_err(|e| e.to_string())?;
+        if !queue.running.contains_key(&id) {
+            return Err(format!("Download {} is not running from the queue", id));
+        }
+        queue.requeued.insert(id.clone());
+    }
+    kill_download(&app, &id, true)
+}
+
+/// Stops the queue from starting new downloads; running ones finish normally.
+#[tauri::command]
+pub fn pause_queue(app: AppHandle) -> Result<QueueState, String> {
+    DOWNLOAD_QUEUE.lock().map_err(|e| e.to_string())?.paused = true;
+    let state = get_queue_state()?;
+    let _ = app.emit("queue-state", state.clone());
+    Ok(state)
+}
+
+#[tauri::command]
+pub async fn resume_queue(app: AppHandle) -> Result<QueueState, String> {
+    DOWNLOAD_QUEUE.lock().map_err(|e| e.to_string())?.paused = false;
+    pump_queue(&app);
+    let state = get_queue_state()?;
+    let _ = app.emit("queue-state", state.clone());
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn get_queue_state() -> Result<QueueState, String> {
+    let queue = DOWNLOAD_QUEUE.lock().map_err(|e| e.to_string())?;
+    let mut running: Vec<String> = queue.running.keys().cloned().collect();
+    running.sort();
+
+    Ok(QueueState {
+        paused: queue.paused,
+        max_concurrent: max_concurrent_downloads(),
+        rate_limit: scheduled_rate_limit(&current_settings()),
+        running,
+        queued: queue
+            .pending
+            .iter()
+            .map(|job| QueuedDownload {
+                id: job.id.clone(),
+                url: job.url.clone(),
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,108 @@
+use chrono::Timelike;
+use regex::Regex;
+
+use crate::models::{AppSettings, RateLimitWindow};
+
+/// "HH:MM" (24-hour) as minutes past midnight.
+fn parse_time_of_day(value: &str) -> Result<u32, String> {
+    let time_re = Regex::new(r"^(\d{1,2}):(\d{2})$").unwrap();
+    let invalid = || format!("Invalid time {:?}; use 24-hour \"HH:MM\"", value);
+    let caps = time_re.captures(value.trim()).ok_or_else(invalid)?;
+    let hours: u32 = caps[1].parse().map_err(|_| invalid())?;
+    let minutes: u32 = caps[2].parse().map_err(|_| invalid())?;
+    if hours > 23 || minutes > 59 {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+/// Normalizes a `--limit-rate` value such as "2M" or "500K"; empty means
+/// unthrottled.
+pub fn rate_limit_value(value: &str) -> Result<Option<String>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let rate_re = Regex::new(r"^(\d+(?:\.\d+)?)([kKmMgG]?)$").unwrap();
+    let caps = rate_re.captures(value).ok_or_else(|| {
+        format!("Invalid rate limit {:?}; use a rate like \"2M\" or \"500K\"", value)
+    })?;
+    if caps[1].parse::<f64>().map_or(true, |rate| rate <= 0.0) {
+        return Err("Rate limit must be greater than zero".to_string());
+    }
+    Ok(Some(format!("{}{}", &caps[1], caps[2].to_ascii_uppercase())))
+}
+
+pub fn validate_rate_limit_schedule(settings: &AppSettings) -> Result<(), String> {
+    for window in &settings.rate_limit_schedule {
+        if parse_time_of_day(&window.start)? == parse_time_of_day(&window.end)? {
+            return Err(format!(
+                "Rate limit window {}-{} is empty; start and end must differ",
+                window.start, window.end
+            ));
+        }
+        window.limit.as_deref().map(rate_limit_value).transpose()?;
+    }
+    Ok(())
+}
+
+/// A window whose end is before its start runs past midnight.
+fn window_contains(window: &RateLimitWindow, minute: u32) -> bool {
+    let (Ok(start), Ok(end)) = (parse_time_of_day(&window.start), parse_time_of_day(&window.end))
+    else {
+        return false;
+    };
+    if start <= end {
+        (start..end).contains(&minute)
+    } else {
+        minute >= start || minute < end
+    }
+}
+
+/// Local minutes past midnight.
+fn local_minute_of_day() -> u32 {
+    let now = chrono::Local::now();
+    now.hour() * 60 + now.minute()
+}
+
+/// `--limit-rate` for a download starting now: the first schedule window
+/// covering the local time, unthrottled outside all of them.
+pub fn scheduled_rate_limit(settings: &AppSettings) -> Option<String> {
+    if settings.rate_limit_schedule.is_empty() {
+        return None;
+    }
+    let minute = local_minute_of_day();
+    let window = settings
+        .rate_limit_schedule
+        .iter()
+        .find(|window| window_contains(window, minute))?;
+    rate_limit_value(window.limit.as_deref()?).ok().flatten()
+}
+
+/// Whether `extra_args` set their own download rate, which then wins over
+/// the schedule.
+pub fn sets_rate_limit(extra_args: &[String]) -> bool {
+    extra_args.iter().any(|arg| {
+        arg == "--limit-rate"
+            || arg.starts_with("--limit-rate=")
+            || arg.strip_prefix("-r").is_some_and(|rest| {
+                rest.is_empty() || rest.starts_with(|c: char| c.is_ascii_digit())
+            })
+    })
+}
+
+/// Fills the scheduled `--limit-rate` slot at `index` of download args built
+/// by `build_download_args` with `rate`, or empties it for `None`.
+pub fn set_rate_limit(args: &mut Vec<String>, index: usize, rate: Option<String>) {
+    let filled = args.get(index).is_some_and(|arg| arg == "--limit-rate");
+    match (filled, rate) {
+        (true, Some(rate)) => args[index + 1] = rate,
+        (true, None) => {
+            args.drain(index..index + 2);
+        }
+        (false, Some(rate)) => {
+            args.splice(index..index, ["--limit-rate".to_string(), rate]);
+        }
+        (false, None) => {}
+    }
+}
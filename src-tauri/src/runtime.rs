@@ -0,0 +1,243 @@
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::ShellExt;
+use tauri_plugin_shell::process::Command;
+
+use crate::diagnostics::DIAGNOSTIC_VIDEO_URL;
+use crate::errors::{CommandError, classify_ytdlp_error};
+use crate::models::JsRuntimeStatus;
+use crate::settings::current_settings;
+use crate::state::JS_RUNTIME_STATUS;
+
+const DEFAULT_JS_RUNTIME: &str = "node";
+/// Runtimes yt-dlp can use for YouTube's JS challenges.
+pub const SUPPORTED_JS_RUNTIMES: &[&str] = &["node", "deno", "bun", "quickjs"];
+
+/// `--js-runtimes` value from the settings.
+pub fn js_runtime() -> String {
+    current_settings()
+        .js_runtime
+        .map(|runtime| runtime.trim().to_string())
+        .filter(|runtime| !runtime.is_empty())
+        .unwrap_or_else(|| DEFAULT_JS_RUNTIME.to_string())
+}
+
+/// `custom_ytdlp_path` from the settings when it points at a file.
+pub fn custom_ytdlp_path() -> Option<String> {
+    let path = current_settings()
+        .custom_ytdlp_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())?;
+    if Path::new(&path).is_file() {
+        Some(path)
+    } else {
+        tracing::warn!("Custom yt-dlp {:?} not found; using the bundled one", path);
+        None
+    }
+}
+
+/// The yt-dlp every command runs: `custom_ytdlp_path` from the settings when
+/// it points at a file, the bundled sidecar otherwise.
+pub fn ytdlp_command(app: &AppHandle) -> Result<Command, tauri_plugin_shell::Error> {
+    match custom_ytdlp_path() {
+        Some(path) => Ok(app.shell().command(path)),
+        None => app.shell().sidecar("yt-dlp"),
+    }
+}
+
+/// `--version` of the yt-dlp at `path`, to check a custom path before
+/// saving it as `custom_ytdlp_path`.
+#[tauri::command]
+pub async fn test_custom_ytdlp(app: AppHandle, path: String) -> Result<String, String> {
+    let path = path.trim();
+    if !Path::new(path).is_file() {
+        return Err(format!("{:?} is not a file", path));
+    }
+    let output = app
+        .shell()
+        .command(path)
+        .args(vec!["--version"])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {:?}: {}", path, e))?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || version.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "{:?} did not report a yt-dlp version: {}",
+            path,
+            stderr.trim()
+        ));
+    }
+    Ok(version)
+}
+
+/// `--cache-dir` shared by every yt-dlp run, so the remote `ejs` components
+/// are downloaded once instead of on each fresh process.
+pub fn ytdlp_cache_dir(app: &AppHandle) -> String {
+    current_settings()
+        .ytdlp_cache_dir
+        .map(|dir| dir.trim().to_string())
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            app.path()
+                .app_cache_dir()
+                .ok()
+                .map(|dir| dir.join("yt-dlp"))
+        })
+        .unwrap_or_else(|| std::env::temp_dir().join("dlpgui_ytdlp_cache"))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Args every yt-dlp run that may hit YouTube needs: the JS runtime for its
+/// challenges and the solver scripts, cached in `ytdlp_cache_dir`.
+pub fn base_ytdlp_args(app: &AppHandle) -> Vec<String> {
+    vec![
+        "--js-runtimes".to_string(),
+        js_runtime(),
+        "--remote-components".to_string(),
+        "ejs:github".to_string(),
+        "--cache-dir".to_string(),
+        ytdlp_cache_dir(app),
+    ]
+}
+
+pub fn validate_js_runtime(runtime: Option<&str>) -> Result<(), String> {
+    let Some(runtime) = runtime.map(str::trim).filter(|runtime| !runtime.is_empty()) else {
+        return Ok(());
+    };
+    let name = runtime.split(':').next().unwrap_or(runtime);
+    if SUPPORTED_JS_RUNTIMES.contains(&name) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unsupported JS runtime {:?}; use one of {}",
+            name,
+            SUPPORTED_JS_RUNTIMES.join(", ")
+        ))
+    }
+}
+
+/// Splits "name:path" into the executable to probe; a bare name is looked up on PATH.
+fn runtime_executable(runtime: &str) -> (&str, &str) {
+    match runtime.split_once(':') {
+        // Keep Windows drive letters ("node:C:\...") inside the path.
+        Some((name, path)) if !path.is_empty() => (name, path),
+        _ => (runtime, runtime),
+    }
+}
+
+async fn runtime_version(app: &AppHandle, executable: &str) -> Option<String> {
+    let output = app
+        .shell()
+        .command(executable)
+        .args(vec!["--version"])
+        .output()
+        .await
+        .ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !version.is_empty()).then_some(version)
+}
+
+/// Probes the configured runtime and, when it's missing, the other supported
+/// ones, then remembers the result.
+pub async fn detect_js_runtime(app: &AppHandle) -> JsRuntimeStatus {
+    let runtime = js_runtime();
+    let (name, executable) = runtime_executable(&runtime);
+    let version = runtime_version(app, executable).await;
+
+    let mut alternatives = Vec::new();
+    if version.is_none() {
+        for candidate in SUPPORTED_JS_RUNTIMES
+            .iter()
+            .filter(|candidate| **candidate != name)
+        {
+            if runtime_version(app, candidate).await.is_some() {
+                alternatives.push(candidate.to_string());
+            }
+        }
+    }
+
+    let status = JsRuntimeStatus {
+        available: version.is_some(),
+        runtime: name.to_string(),
+        version,
+        alternatives,
+    };
+    if let Ok(mut stored) = JS_RUNTIME_STATUS.lock() {
+        *stored = Some(status.clone());
+    }
+    status
+}
+
+/// Startup check; emits `js-runtime-missing` so the UI can explain the
+/// problem before the first YouTube fetch fails.
+pub fn check_js_runtime_on_startup(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let status = detect_js_runtime(&app).await;
+        if !status.available {
+            tracing::warn!("JS runtime {} not found", status.runtime);
+            let _ = app.emit("js-runtime-missing", status);
+        }
+    });
+}
+
+/// Re-runs the JS runtime check, e.g. after the user installed node or
+/// switched runtimes.
+#[tauri::command]
+pub async fn check_js_runtime(app: AppHandle) -> JsRuntimeStatus {
+    detect_js_runtime(&app).await
+}
+
+/// The last JS runtime check, without probing again; `None` until the
+/// startup check has finished.
+#[tauri::command]
+pub fn get_js_runtime_status() -> Option<JsRuntimeStatus> {
+    JS_RUNTIME_STATUS
+        .lock()
+        .ok()
+        .and_then(|status| status.clone())
+}
+
+/// Fills the yt-dlp cache with the remote components by extracting a known
+/// video; with `refresh` the cache is wiped first so fresh copies are fetched.
+/// Returns the cache directory.
+#[tauri::command]
+pub async fn warm_remote_components(
+    app: AppHandle,
+    refresh: Option<bool>,
+) -> Result<String, CommandError> {
+    let cache_dir = ytdlp_cache_dir(&app);
+    if refresh.unwrap_or(false) && PathBuf::from(&cache_dir).exists() {
+        std::fs::remove_dir_all(&cache_dir)
+            .map_err(|e| format!("Failed to clear {:?}: {}", cache_dir, e))?;
+    }
+    std::fs::create_dir_all(&cache_dir)
+        .map_err(|e| format!("Failed to create {:?}: {}", cache_dir, e))?;
+
+    let mut args = base_ytdlp_args(&app);
+    args.extend([
+        "--simulate".to_string(),
+        "--no-playlist".to_string(),
+        DIAGNOSTIC_VIDEO_URL.to_string(),
+    ]);
+    let output = ytdlp_command(&app)
+        .map_err(|e| e.to_string())?
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(classify_ytdlp_error(
+            &stderr,
+            "Failed to fetch remote components",
+        ));
+    }
+    Ok(cache_dir)
+}
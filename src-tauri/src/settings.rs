@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager};
+
+use crate::args::{resolve_output_template, validate_aria2c_settings, validate_extra_args};
+use crate::logging::{apply_log_settings, validate_log_settings};
+use crate::models::{AppSettings, QualityPreference};
+use crate::ratelimit::validate_rate_limit_schedule;
+use crate::runtime::validate_js_runtime;
+use crate::state::APP_SETTINGS;
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(config_dir.join(SETTINGS_FILE_NAME))
+}
+
+pub fn load_settings(app: &AppHandle) {
+    let settings = settings_path(app)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| match serde_json::from_str::<AppSettings>(&contents) {
+            Ok(settings) => Some(settings),
+            Err(err) => {
+                tracing::warn!("Ignoring unreadable settings file: {}", err);
+                None
+            }
+        })
+        .unwrap_or_default();
+
+    apply_log_settings(&settings);
+    if let Ok(mut state) = APP_SETTINGS.lock() {
+        *state = settings;
+    }
+}
+
+pub fn current_settings() -> AppSettings {
+    APP_SETTINGS
+        .lock()
+        .map(|settings| settings.clone())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_settings() -> AppSettings {
+    current_settings()
+}
+
+fn save_settings(app: &AppHandle, settings: &AppSettings) -> Result<(), String> {
+    let path = settings_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+
+    let contents = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+#[tauri::command]
+pub fn update_settings(app: AppHandle, mut settings: AppSettings) -> Result<AppSettings, String> {
+    validate_extra_args(&settings.default_extra_args)?;
+    resolve_output_template(&settings)?;
+    validate_js_runtime(settings.js_runtime.as_deref())?;
+    validate_aria2c_settings(&settings)?;
+    validate_log_settings(&settings)?;
+    validate_rate_limit_schedule(&settings)?;
+
+    // Hold the lock while saving so a concurrent `remember_quality` can't be
+    // lost; the frontend's copy of the remembered qualities may be stale.
+    let mut state = APP_SETTINGS.lock().map_err(|e| e.to_string())?;
+    settings.host_qualities = state.host_qualities.clone();
+    save_settings(&app, &settings)?;
+    apply_log_settings(&settings);
+    *state = settings.clone();
+
+    Ok(settings)
+}
+
+/// Normalized host used to key per-site preferences: lowercase, without
+/// credentials, port or a leading `www.`/`m.`; `youtu.be` counts as YouTube.
+pub fn url_host(url: &str) -> Option<String> {
+    let rest = url.trim().split_once("://").map_or(url.trim(), |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?.to_ascii_lowercase();
+    let host = host
+        .strip_prefix("www.")
+        .or_else(|| host.strip_prefix("m."))
+        .unwrap_or(&host);
+
+    match host {
+        "" => None,
+        "youtu.be" => Some("youtube.com".to_string()),
+        host => Some(host.to_string()),
+    }
+}
+
+/// Remembers `preference` as the quality for `url`'s host.
+pub fn remember_quality(app: &AppHandle, url: &str, preference: QualityPreference) {
+    let Some(host) = url_host(url) else {
+        return;
+    };
+
+    let Ok(mut state) = APP_SETTINGS.lock() else {
+        return;
+    };
+    if state.host_qualities.get(&host) == Some(&preference) {
+        return;
+    }
+    state.host_qualities.insert(host, preference);
+
+    if let Err(err) = save_settings(app, &state) {
+        tracing::warn!("Failed to remember quality preference: {}", err);
+    }
+}
+
+/// The quality last used on `url`'s host, falling back to the global default.
+#[tauri::command]
+pub fn get_default_quality(url: String) -> Option<QualityPreference> {
+    let settings = current_settings();
+    url_host(&url)
+        .and_then(|host| settings.host_qualities.get(&host).cloned())
+        .or(settings.default_quality)
+}
@@ -1,10 +1,19 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use tauri_plugin_shell::process::CommandChild;
+use tempfile::TempDir;
+use tokio::sync::{oneshot, watch};
+use tracing_appender::rolling::RollingFileAppender;
+use tracing_subscriber::{EnvFilter, Registry, reload};
 
-use crate::models::ExtensionDownloadRequest;
+use crate::models::{
+    AppSettings, DownloadJob, DownloadStats, ExtensionDownloadRequest, JsRuntimeStatus,
+    LaunchRequest,
+};
 
 pub const EXTENSION_BRIDGE_HOST: &str = "127.0.0.1";
 pub const EXTENSION_BRIDGE_PORT: u16 = 46321;
@@ -12,13 +21,88 @@ pub const MAIN_WINDOW_LABEL: &str = "main";
 pub const TRAY_OPEN_ID: &str = "tray-open";
 pub const TRAY_QUIT_ID: &str = "tray-quit";
 
+pub struct CachedVideoJson {
+    pub fetched_at: Instant,
+    pub json: serde_json::Value,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct DownloadQueue {
+    /// While set, queued jobs wait; running downloads are left alone.
+    pub paused: bool,
+    pub pending: VecDeque<DownloadJob>,
+    /// Jobs started from the queue, kept so they can be requeued.
+    pub running: HashMap<String, DownloadJob>,
+    /// Running jobs being stopped by `requeue_download`; they go back to the
+    /// end of `pending` once their task exits.
+    pub requeued: HashSet<String>,
+}
+
+/// A download task and the job it was started from. `finished` reports
+/// closed once the task has exited and cleaned up its temp dir.
+pub struct RunningDownload {
+    pub job: DownloadJob,
+    pub finished: watch::Receiver<()>,
+}
+
+/// The daily log files and the filter that controls what goes into them.
+pub struct LogFile {
+    pub dir: PathBuf,
+    pub appender: RollingFileAppender,
+    pub filter: reload::Handle<EnvFilter, Registry>,
+}
+
 pub static EXTENSION_BRIDGE_READY: AtomicBool = AtomicBool::new(false);
+/// Set once the frontend has collected the launch URLs queued at startup.
+pub static LAUNCH_URLS_TAKEN: AtomicBool = AtomicBool::new(false);
 
 lazy_static::lazy_static! {
     pub static ref ACTIVE_DOWNLOADS: Arc<Mutex<HashMap<String, CommandChild>>> =
         Arc::new(Mutex::new(HashMap::new()));
+    pub static ref ACTIVE_FETCHES: Arc<Mutex<HashMap<String, CommandChild>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+    /// Every task started by `spawn_download`, so `restart_download` can rerun it.
+    pub static ref RUNNING_DOWNLOADS: Arc<Mutex<HashMap<String, RunningDownload>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+    /// Running playlist downloads, mapped to the id of the video currently downloading.
+    pub static ref ACTIVE_PLAYLISTS: Arc<Mutex<HashMap<String, Option<String>>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+    /// Paused downloads; the sender is set once the task is waiting and takes
+    /// `true` to resume or `false` to cancel.
+    pub static ref PAUSED_DOWNLOADS: Arc<Mutex<HashMap<String, Option<oneshot::Sender<bool>>>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+    /// Downloads being cancelled with `keep_partial`, so their task keeps the temp dir.
+    pub static ref KEEP_PARTIAL_DOWNLOADS: Arc<Mutex<HashSet<String>>> =
+        Arc::new(Mutex::new(HashSet::new()));
+    /// Last result of the JS runtime check run at startup.
+    pub static ref JS_RUNTIME_STATUS: Arc<Mutex<Option<JsRuntimeStatus>>> =
+        Arc::new(Mutex::new(None));
+    pub static ref DOWNLOAD_QUEUE: Arc<Mutex<DownloadQueue>> =
+        Arc::new(Mutex::new(DownloadQueue::default()));
+    pub static ref DOWNLOAD_STATS: Arc<Mutex<HashMap<String, DownloadStats>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+    pub static ref LOG_FILE: Arc<Mutex<Option<LogFile>>> = Arc::new(Mutex::new(None));
+    pub static ref APP_SETTINGS: Arc<Mutex<AppSettings>> =
+        Arc::new(Mutex::new(AppSettings::default()));
+    /// Parsed `-J` output and its warnings, keyed by the full yt-dlp argument list.
+    pub static ref FORMAT_CACHE: Arc<Mutex<HashMap<String, CachedVideoJson>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+    /// Session cookie files keyed by the `--cookies-from-browser` spec they came from.
+    pub static ref COOKIE_CACHE: Arc<Mutex<HashMap<String, PathBuf>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+    /// This session's private folder for `COOKIE_CACHE` files, removed on exit.
+    pub static ref COOKIE_CACHE_DIR: Arc<Mutex<Option<TempDir>>> = Arc::new(Mutex::new(None));
+    /// Held across each load-modify-save of the downloaded-videos file.
+    pub static ref DOWNLOADED_VIDEOS_LOCK: Arc<Mutex<()>> = Arc::new(Mutex::new(()));
+    /// Held across each load-modify-save of the resumable and playlist journals.
+    pub static ref JOURNAL_LOCK: Arc<Mutex<()>> = Arc::new(Mutex::new(()));
+    pub static ref EXTRACTOR_CACHE: Arc<Mutex<Option<Vec<String>>>> =
+        Arc::new(Mutex::new(None));
     pub static ref EXTENSION_BRIDGE_ERROR: Arc<Mutex<Option<String>>> =
         Arc::new(Mutex::new(None));
+    pub static ref PENDING_LAUNCH_URLS: Arc<Mutex<Vec<LaunchRequest>>> =
+        Arc::new(Mutex::new(Vec::new()));
     pub static ref PENDING_EXTENSION_REQUESTS: Arc<Mutex<Vec<ExtensionDownloadRequest>>> =
         Arc::new(Mutex::new(Vec::new()));
 }
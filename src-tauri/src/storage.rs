@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use crate::models::DownloadsSizeInfo;
+
+/// Upper bound on visited entries so a huge or looping tree can't stall the command.
+const MAX_SCANNED_ENTRIES: usize = 200_000;
+
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "mp4", "mkv", "webm", "mov", "avi", "flv", "m4v", "ts", "mp3", "m4a", "aac", "opus", "ogg",
+    "flac", "wav", "wma",
+];
+
+fn is_media_file(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| MEDIA_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+fn scan_directory(root: PathBuf, media_only: bool) -> DownloadsSizeInfo {
+    let mut info = DownloadsSizeInfo {
+        total_bytes: 0,
+        file_count: 0,
+        skipped_entries: 0,
+        truncated: false,
+    };
+    let mut pending = vec![root];
+    let mut scanned = 0usize;
+
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => {
+                info.skipped_entries += 1;
+                continue;
+            }
+        };
+
+        for entry in entries {
+            scanned += 1;
+            if scanned > MAX_SCANNED_ENTRIES {
+                info.truncated = true;
+                return info;
+            }
+
+            let Ok(entry) = entry else {
+                info.skipped_entries += 1;
+                continue;
+            };
+            // file_type() does not follow symlinks, which keeps link loops out.
+            let Ok(file_type) = entry.file_type() else {
+                info.skipped_entries += 1;
+                continue;
+            };
+
+            let path = entry.path();
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() && (!media_only || is_media_file(&path)) {
+                match entry.metadata() {
+                    Ok(metadata) => {
+                        info.total_bytes += metadata.len();
+                        info.file_count += 1;
+                    }
+                    Err(_) => info.skipped_entries += 1,
+                }
+            }
+        }
+    }
+
+    info
+}
+
+/// Bytes the current user may still write on the volume holding `path`, or
+/// `None` when the platform can't tell. Missing trailing components are
+/// skipped, so a download dir that doesn't exist yet still resolves.
+pub fn available_space(path: &Path) -> Option<u64> {
+    let path = path.ancestors().find(|ancestor| ancestor.exists())?;
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+        let wide: Vec<u16> = path
+            .as_os_str()
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+        let mut available = 0u64;
+        // SAFETY: `wide` is NUL-terminated and outlives the call; both totals
+        // are optional out-params.
+        let ok = unsafe {
+            GetDiskFreeSpaceExW(
+                wide.as_ptr(),
+                &mut available,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        (ok != 0).then_some(available)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        // SAFETY: `c_path` is NUL-terminated and `stat` is a plain out-param.
+        let stat = unsafe {
+            let mut stat: libc::statvfs = std::mem::zeroed();
+            (libc::statvfs(c_path.as_ptr(), &mut stat) == 0).then_some(stat)
+        }?;
+        // f_bavail counts the blocks unprivileged users may still use.
+        Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+}
+
+#[tauri::command]
+pub async fn get_downloads_size(
+    path: String,
+    media_only: Option<bool>,
+) -> Result<DownloadsSizeInfo, String> {
+    let root = PathBuf::from(&path);
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", path));
+    }
+
+    tokio::task::spawn_blocking(move || scan_directory(root, media_only.unwrap_or(false)))
+        .await
+        .map_err(|e| format!("Failed to scan downloads: {}", e))
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use tauri::AppHandle;
+
+use crate::args::{auth_args, redact_args};
+use crate::downloads::{fetch_formats, resolve_extra_args};
+use crate::errors::{CommandError, classify_ytdlp_error};
+use crate::models::{AuthCredentials, StoryboardOption};
+use crate::process::run_fetch;
+use crate::runtime::{base_ytdlp_args, ytdlp_command};
+
+/// Storyboards are the `mhtml` formats yt-dlp labels "storyboard"; they have
+/// neither a video nor an audio codec, so the quality list skips them.
+pub fn storyboard_options(formats: &[serde_json::Value]) -> Vec<StoryboardOption> {
+    let as_u32 = |value: &serde_json::Value| value.as_u64().unwrap_or(0) as u32;
+    let mut storyboards: Vec<StoryboardOption> = formats
+        .iter()
+        .filter(|format| {
+            format["format_note"].as_str() == Some("storyboard")
+                || format["protocol"].as_str() == Some("mhtml")
+        })
+        .map(|format| StoryboardOption {
+            format_id: format["format_id"].as_str().unwrap_or("").to_string(),
+            width: as_u32(&format["width"]),
+            height: as_u32(&format["height"]),
+            columns: as_u32(&format["columns"]),
+            rows: as_u32(&format["rows"]),
+            fragment_count: format["fragments"].as_array().map_or(0, Vec::len),
+            fps: format["fps"].as_f64(),
+        })
+        .filter(|storyboard| !storyboard.format_id.is_empty())
+        .collect();
+    storyboards.sort_by_key(|storyboard| std::cmp::Reverse(storyboard.width * storyboard.height));
+    storyboards
+}
+
+#[tauri::command]
+pub async fn list_storyboards(
+    app: AppHandle,
+    url: String,
+    request_id: Option<String>,
+    auth: Option<AuthCredentials>,
+) -> Result<Vec<StoryboardOption>, CommandError> {
+    let formats = fetch_formats(app, url, None, request_id, auth, None, None, None).await?;
+    Ok(formats.storyboards)
+}
+
+/// Downloads storyboard `format_id` into `download_dir` as an `.mhtml` file
+/// holding every grid image, and returns its path.
+#[tauri::command]
+pub async fn download_storyboard(
+    app: AppHandle,
+    url: String,
+    format_id: String,
+    download_dir: String,
+    request_id: Option<String>,
+    auth: Option<AuthCredentials>,
+) -> Result<String, CommandError> {
+    let format_id = format_id.trim();
+    if format_id.is_empty() {
+        return Err("Format id cannot be empty".into());
+    }
+    if !Path::new(&download_dir).is_dir() {
+        return Err(format!("Download folder {:?} does not exist", download_dir).into());
+    }
+
+    let mut args = base_ytdlp_args(&app);
+    args.extend([
+        "--no-playlist".to_string(),
+        "--no-progress".to_string(),
+        "-f".to_string(),
+        format_id.to_string(),
+        "-P".to_string(),
+        download_dir,
+        "-o".to_string(),
+        "%(title)s [%(id)s].storyboard-%(format_id)s.%(ext)s".to_string(),
+        "--print".to_string(),
+        "after_move:filepath".to_string(),
+    ]);
+    args.extend(resolve_extra_args(None)?);
+    if let Some(auth) = auth.as_ref() {
+        args.extend(auth_args(auth));
+    }
+    args.push(url);
+    tracing::debug!("yt-dlp args: {:?}", redact_args(&args));
+
+    let sidecar_command = ytdlp_command(&app).map_err(|e| e.to_string())?;
+    let output = run_fetch(sidecar_command.args(args), request_id.as_deref()).await?;
+    if !output.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(classify_ytdlp_error(&stderr, "Storyboard download failed"));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .map(|path| path.to_string())
+        .ok_or_else(|| "yt-dlp did not report the storyboard file".into())
+}
@@ -0,0 +1,272 @@
+use std::path::PathBuf;
+
+use regex::Regex;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_shell::process::CommandEvent;
+use tokio::sync::oneshot;
+
+use crate::args::{auth_args, redact_args};
+use crate::downloads::{
+    ProgressThrottle, clear_download_stats, progress_event, record_download_stats,
+    resolve_extra_args,
+};
+use crate::errors::{CommandError, classify_ytdlp_error};
+use crate::models::{AuthCredentials, DirectUrl, StreamInfo};
+use crate::process::run_fetch;
+use crate::runtime::{base_ytdlp_args, ytdlp_command};
+use crate::state::ACTIVE_DOWNLOADS;
+
+/// Single-file formats first: they are written front to back, so a player can
+/// open the file while it is still growing.
+const DEFAULT_STREAM_FORMAT: &str = "b[protocol^=http][acodec!=none][vcodec!=none]/b";
+
+fn stream_root() -> PathBuf {
+    std::env::temp_dir().join("dlpgui_stream")
+}
+
+/// Removes the folders of finished streams; cancelled and failed ones clean
+/// up after themselves as soon as yt-dlp exits.
+fn remove_finished_streams() {
+    let Ok(entries) = std::fs::read_dir(stream_root()) else {
+        return;
+    };
+    let active: Vec<String> = ACTIVE_DOWNLOADS
+        .lock()
+        .map(|downloads| downloads.keys().cloned().collect())
+        .unwrap_or_default();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !active.contains(&name) {
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// Downloads `url` into the OS temp dir without a `.part` file so an external
+/// player can watch while the download continues. Resolves once yt-dlp has
+/// picked the destination; progress and the final status arrive through the
+/// usual download events, and `cancel_download(id)` stops it. A finished
+/// stream's file stays until the next stream starts.
+#[tauri::command]
+pub async fn start_stream(
+    app: AppHandle,
+    id: String,
+    url: String,
+    format_string: Option<String>,
+    auth: Option<AuthCredentials>,
+) -> Result<StreamInfo, CommandError> {
+    remove_finished_streams();
+    let stream_dir = stream_root().join(&id);
+    std::fs::create_dir_all(&stream_dir)
+        .map_err(|e| format!("Failed to create stream directory: {}", e))?;
+
+    let format_string = format_string
+        .map(|format| format.trim().to_string())
+        .filter(|format| !format.is_empty())
+        .unwrap_or_else(|| DEFAULT_STREAM_FORMAT.to_string());
+
+    let mut args = base_ytdlp_args(&app);
+    args.extend([
+        "--progress".to_string(),
+        "--newline".to_string(),
+        "--no-update".to_string(),
+        "--no-playlist".to_string(),
+        "--no-part".to_string(),
+        "-f".to_string(),
+        format_string,
+        "-P".to_string(),
+        stream_dir.to_string_lossy().to_string(),
+        "-o".to_string(),
+        "%(title).100B [%(id)s].%(ext)s".to_string(),
+    ]);
+    args.extend(resolve_extra_args(None)?);
+    if let Some(auth) = auth.as_ref() {
+        args.extend(auth_args(auth));
+    }
+    args.push(url);
+    tracing::debug!("yt-dlp args: {:?}", redact_args(&args));
+
+    let sidecar_command = ytdlp_command(&app).map_err(|e| e.to_string())?;
+    let (mut rx, child) = sidecar_command.args(args).spawn().map_err(|e| {
+        let _ = std::fs::remove_dir_all(&stream_dir);
+        e.to_string()
+    })?;
+    ACTIVE_DOWNLOADS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(id.clone(), child);
+
+    let (ready_tx, ready_rx) = oneshot::channel::<Result<PathBuf, CommandError>>();
+    let app_clone = app.clone();
+    let id_clone = id.clone();
+    let stream_dir_clone = stream_dir.clone();
+
+    tokio::spawn(async move {
+        let mut ready_tx = Some(ready_tx);
+        let mut stream_path: Option<PathBuf> = None;
+        let mut error_lines: Vec<String> = Vec::new();
+        let mut progress_throttle = ProgressThrottle::default();
+        let re_destination = Regex::new(r"\[download\]\s+Destination:\s+(.+)").unwrap();
+        let re_already_downloaded =
+            Regex::new(r"\[download\]\s+(.+?) has already been downloaded").unwrap();
+        let re_progress = Regex::new(
+            r"\[download\]\s+(\d+\.?\d*)%\s+of\s+(~?[\d.]+\s*[kKMGT]?i?B)(?:\s+at\s+(\S+)\s+ETA\s+(\S+))?",
+        )
+        .unwrap();
+
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(line) => {
+                    let line_str = String::from_utf8_lossy(&line).trim().to_string();
+                    if let Some(caps) = re_destination
+                        .captures(&line_str)
+                        .or_else(|| re_already_downloaded.captures(&line_str))
+                    {
+                        let path = PathBuf::from(caps[1].trim());
+                        if let Some(ready_tx) = ready_tx.take() {
+                            let _ = ready_tx.send(Ok(path.clone()));
+                        }
+                        stream_path = Some(path);
+                    } else if let Some(caps) = re_progress.captures(&line_str) {
+                        let raw_percent = caps[1].parse::<f32>().unwrap_or(0.0);
+                        let progress = progress_event(
+                            &id_clone,
+                            raw_percent,
+                            raw_percent,
+                            caps[2].trim(),
+                            caps.get(3).map_or("...", |speed| speed.as_str()),
+                            caps.get(4).map_or("...", |eta| eta.as_str()),
+                            "streaming",
+                        );
+                        record_download_stats(&progress);
+                        progress_throttle.emit(&app_clone, progress);
+                    }
+                }
+                CommandEvent::Stderr(line) => {
+                    let line_str = String::from_utf8_lossy(&line).trim().to_string();
+                    if line_str.starts_with("ERROR:") {
+                        error_lines.push(line_str);
+                    }
+                }
+                CommandEvent::Terminated(payload) => {
+                    progress_throttle.flush(&app_clone);
+                    let succeeded = payload.code == Some(0);
+                    let was_cancelled = ACTIVE_DOWNLOADS
+                        .lock()
+                        .map(|downloads| !downloads.contains_key(&id_clone))
+                        .unwrap_or(false);
+                    let error = (!succeeded && !was_cancelled)
+                        .then(|| classify_ytdlp_error(&error_lines.join("\n"), "Stream failed"));
+
+                    if succeeded && stream_path.is_none() {
+                        stream_path = std::fs::read_dir(&stream_dir_clone)
+                            .ok()
+                            .and_then(|entries| {
+                                entries
+                                    .flatten()
+                                    .map(|entry| entry.path())
+                                    .find(|path| path.is_file())
+                            });
+                    }
+                    if let Some(ready_tx) = ready_tx.take() {
+                        let _ = ready_tx.send(match (&error, &stream_path) {
+                            (Some(error), _) => Err(error.clone()),
+                            (None, Some(path)) if succeeded => Ok(path.clone()),
+                            _ => Err("Stream ended before any data was written".into()),
+                        });
+                    }
+                    if !was_cancelled {
+                        let _ = app_clone.emit(
+                            "download-status",
+                            serde_json::json!({
+                                "id": id_clone.clone(),
+                                "status": if succeeded { "completed" } else { "error" },
+                                "error": error,
+                                "final_path": stream_path
+                                    .as_ref()
+                                    .map(|path| path.to_string_lossy().to_string()),
+                            }),
+                        );
+                    }
+                    if !succeeded {
+                        let _ = std::fs::remove_dir_all(&stream_dir_clone);
+                    }
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        if let Ok(mut downloads) = ACTIVE_DOWNLOADS.lock() {
+            downloads.remove(&id_clone);
+        }
+        clear_download_stats(&id_clone);
+    });
+
+    let path = ready_rx
+        .await
+        .map_err(|_| CommandError::from("Stream ended unexpectedly"))??;
+
+    Ok(StreamInfo {
+        id,
+        path: path.to_string_lossy().to_string(),
+    })
+}
+
+/// Resolves the CDN URL(s) of `format_id` with `--get-url`, for handing the
+/// stream to another tool without downloading it here.
+#[tauri::command]
+pub async fn get_direct_url(
+    app: AppHandle,
+    url: String,
+    format_id: String,
+    request_id: Option<String>,
+    auth: Option<AuthCredentials>,
+) -> Result<DirectUrl, CommandError> {
+    let format_id = format_id.trim();
+    if format_id.is_empty() {
+        return Err("Format id cannot be empty".into());
+    }
+
+    let mut args = base_ytdlp_args(&app);
+    args.extend([
+        "--get-url".to_string(),
+        "--no-playlist".to_string(),
+        "-f".to_string(),
+        format_id.to_string(),
+    ]);
+    args.extend(resolve_extra_args(None)?);
+    if let Some(auth) = auth.as_ref() {
+        args.extend(auth_args(auth));
+    }
+    args.push(url);
+    tracing::debug!("yt-dlp args: {:?}", redact_args(&args));
+
+    let sidecar_command = ytdlp_command(&app).map_err(|e| e.to_string())?;
+    let output = run_fetch(sidecar_command.args(args), request_id.as_deref()).await?;
+    if !output.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(classify_ytdlp_error(&stderr, "Failed to resolve the direct URL"));
+    }
+
+    let urls: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("http"))
+        .map(|line| line.to_string())
+        .collect();
+    if urls.is_empty() {
+        return Err("yt-dlp returned no URL for this format".into());
+    }
+
+    // googlevideo URLs carry `expire=<unix time>` either as a query parameter
+    // or as a `/expire/<time>/` path segment on manifest URLs.
+    let expire_re = Regex::new(r"[?&/]expire[=/](\d+)").unwrap();
+    let expires_at = urls
+        .iter()
+        .filter_map(|url| expire_re.captures(url))
+        .filter_map(|caps| caps[1].parse::<u64>().ok())
+        .min();
+
+    Ok(DirectUrl { urls, expires_at })
+}
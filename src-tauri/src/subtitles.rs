@@ -0,0 +1,154 @@
+use std::path::Path;
+
+use regex::Regex;
+use tauri::AppHandle;
+
+use crate::args::{auth_args, redact_args};
+use crate::downloads::resolve_extra_args;
+use crate::errors::{CommandError, classify_ytdlp_error};
+use crate::models::{AuthCredentials, SubtitlePreview};
+use crate::process::run_fetch;
+use crate::runtime::{base_ytdlp_args, ytdlp_command};
+
+const DEFAULT_PREVIEW_LINES: usize = 20;
+const SUBTITLE_EXTENSIONS: &[&str] = &["vtt", "srt", "ass", "ttml", "srv3", "json3"];
+/// The formats `subtitle_text_lines` can read.
+const PREVIEWABLE_SUBTITLE_EXTENSIONS: &[&str] = &["vtt", "srt"];
+
+/// Spoken text of a vtt/srt file: headers, cue numbers, timings and inline
+/// tags are dropped, and the repeats of rolling auto-captions collapsed.
+fn subtitle_text_lines(contents: &str) -> Vec<String> {
+    let tag_re = Regex::new(r"<[^>]*>").unwrap();
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_header = contents.trim_start().starts_with("WEBVTT");
+    for line in contents.lines() {
+        let line = line.trim();
+        if in_header {
+            in_header = !line.is_empty();
+            continue;
+        }
+        if line.is_empty() || line.contains("-->") || line.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        let text = tag_re.replace_all(line, "").trim().to_string();
+        if !text.is_empty() && lines.last() != Some(&text) {
+            lines.push(text);
+        }
+    }
+    lines
+}
+
+fn find_subtitle_file(dir: &Path) -> Option<std::path::PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| SUBTITLE_EXTENSIONS.contains(&ext))
+        })
+}
+
+/// Fetches only the `lang` subtitle track (the auto-generated one with
+/// `automatic`) into a scratch folder and returns its first `max_lines`
+/// lines of text, so the track can be checked before downloading the video.
+#[tauri::command]
+pub async fn preview_subtitles(
+    app: AppHandle,
+    url: String,
+    lang: String,
+    automatic: Option<bool>,
+    max_lines: Option<usize>,
+    request_id: Option<String>,
+    auth: Option<AuthCredentials>,
+) -> Result<SubtitlePreview, CommandError> {
+    let lang = lang.trim().to_string();
+    let language_re = Regex::new(r"^[A-Za-z]{2,3}(-[A-Za-z0-9]+)*$").unwrap();
+    if !language_re.is_match(&lang) {
+        return Err(format!("Invalid subtitle language {:?}", lang).into());
+    }
+    let automatic = automatic.unwrap_or(false);
+    let max_lines = max_lines.unwrap_or(DEFAULT_PREVIEW_LINES).max(1);
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or(0);
+    let temp_dir = std::env::temp_dir()
+        .join("dlpgui_subtitle_preview")
+        .join(format!("{}-{}", std::process::id(), nanos));
+    std::fs::create_dir_all(&temp_dir)
+        .map_err(|e| format!("Failed to create preview directory: {}", e))?;
+
+    let mut args = base_ytdlp_args(&app);
+    args.extend([
+        "--skip-download".to_string(),
+        "--no-playlist".to_string(),
+        if automatic {
+            "--write-auto-subs"
+        } else {
+            "--write-subs"
+        }
+        .to_string(),
+        "--sub-langs".to_string(),
+        lang.clone(),
+        "--sub-format".to_string(),
+        "vtt/srt/best".to_string(),
+        "-P".to_string(),
+        temp_dir.to_string_lossy().to_string(),
+        "-o".to_string(),
+        "preview.%(ext)s".to_string(),
+    ]);
+    args.extend(resolve_extra_args(None)?);
+    if let Some(auth) = auth.as_ref() {
+        args.extend(auth_args(auth));
+    }
+    args.push(url);
+    tracing::debug!("yt-dlp args: {:?}", redact_args(&args));
+
+    let result = async {
+        let sidecar_command = ytdlp_command(&app).map_err(|e| e.to_string())?;
+        let output = run_fetch(sidecar_command.args(args), request_id.as_deref()).await?;
+        if !output.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(classify_ytdlp_error(&stderr, "Subtitle preview failed"));
+        }
+
+        // yt-dlp exits cleanly when the language simply has no track.
+        let path = find_subtitle_file(&temp_dir).ok_or_else(|| {
+            format!(
+                "No {} subtitles in {:?} for this video",
+                if automatic { "auto-generated" } else { "manual" },
+                lang
+            )
+        })?;
+        let format = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !PREVIEWABLE_SUBTITLE_EXTENSIONS.contains(&format.as_str()) {
+            return Err(format!(
+                "The {:?} subtitles are only offered as {}, which can't be previewed",
+                lang, format
+            )
+            .into());
+        }
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read subtitles: {}", e))?;
+        let mut lines = subtitle_text_lines(&contents);
+        let truncated = lines.len() > max_lines;
+        lines.truncate(max_lines);
+
+        Ok(SubtitlePreview {
+            language: lang.clone(),
+            automatic,
+            format,
+            lines,
+            truncated,
+        })
+    }
+    .await;
+
+    let _ = std::fs::remove_dir_all(&temp_dir);
+    result
+}
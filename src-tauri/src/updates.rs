@@ -1,9 +1,18 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use tauri::AppHandle;
-use tauri_plugin_shell::ShellExt;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::AsyncWriteExt;
 
-use crate::models::YtDlpVersionInfo;
+use crate::downloads::clear_format_cache;
+use crate::extractors::clear_extractor_cache;
+use crate::models::{YtDlpUpdateCheck, YtDlpUpdateProgress, YtDlpVersionInfo};
+use crate::runtime::{custom_ytdlp_path, ytdlp_command};
+use crate::settings::current_settings;
+
+const YTDLP_RELEASES_URL: &str = "https://github.com/yt-dlp/yt-dlp/releases/download";
+const UPDATE_CHECK_FILE_NAME: &str = "ytdlp_update_check.json";
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
 
 fn get_ytdlp_path() -> Result<PathBuf, String> {
     let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
@@ -36,9 +45,8 @@ fn get_ytdlp_path() -> Result<PathBuf, String> {
     ))
 }
 
-#[tauri::command]
-pub async fn check_ytdlp_update(app: AppHandle) -> Result<YtDlpVersionInfo, String> {
-    let sidecar_command = app.shell().sidecar("yt-dlp").map_err(|e| e.to_string())?;
+async fn current_ytdlp_version(app: &AppHandle) -> Result<String, String> {
+    let sidecar_command = ytdlp_command(app).map_err(|e| e.to_string())?;
 
     let output = sidecar_command
         .args(vec!["--version"])
@@ -46,8 +54,45 @@ pub async fn check_ytdlp_update(app: AppHandle) -> Result<YtDlpVersionInfo, Stri
         .await
         .map_err(|e| e.to_string())?;
 
-    let current_version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
 
+fn update_check_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let cache_dir = app.path().app_cache_dir().map_err(|e| e.to_string())?;
+    Ok(cache_dir.join(UPDATE_CHECK_FILE_NAME))
+}
+
+fn load_update_check(app: &AppHandle) -> Option<YtDlpUpdateCheck> {
+    let contents = std::fs::read_to_string(update_check_path(app).ok()?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn save_update_check(app: &AppHandle, latest_version: &str) {
+    let check = YtDlpUpdateCheck {
+        checked_at: unix_now(),
+        latest_version: latest_version.to_string(),
+    };
+    let saved = update_check_path(app).and_then(|path| {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let contents = serde_json::to_string(&check).map_err(|e| e.to_string())?;
+        std::fs::write(path, contents).map_err(|e| e.to_string())
+    });
+    if let Err(err) = saved {
+        tracing::warn!("Failed to save the yt-dlp update check: {}", err);
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Tag of the latest yt-dlp release on GitHub.
+async fn latest_ytdlp_version() -> Result<String, String> {
     let client = reqwest::Client::new();
     let response = client
         .get("https://api.github.com/repos/yt-dlp/yt-dlp/releases/latest")
@@ -65,10 +110,17 @@ pub async fn check_ytdlp_update(app: AppHandle) -> Result<YtDlpVersionInfo, Stri
         .await
         .map_err(|e| format!("Failed to parse GitHub response: {}", e))?;
 
-    let latest_version = release["tag_name"]
+    release["tag_name"]
         .as_str()
-        .ok_or("Failed to get latest version tag")?
-        .to_string();
+        .map(|tag| tag.to_string())
+        .ok_or_else(|| "Failed to get latest version tag".to_string())
+}
+
+#[tauri::command]
+pub async fn check_ytdlp_update(app: AppHandle) -> Result<YtDlpVersionInfo, String> {
+    let current_version = current_ytdlp_version(&app).await?;
+    let latest_version = latest_ytdlp_version().await?;
+    save_update_check(&app, &latest_version);
 
     Ok(YtDlpVersionInfo {
         update_available: current_version != latest_version,
@@ -77,29 +129,163 @@ pub async fn check_ytdlp_update(app: AppHandle) -> Result<YtDlpVersionInfo, Stri
     })
 }
 
-#[tauri::command]
-pub async fn update_ytdlp(app: AppHandle) -> Result<String, String> {
-    let ytdlp_path = get_ytdlp_path()?;
-    println!("[DEBUG] Updating yt-dlp at: {:?}", ytdlp_path);
+/// Startup check: emits `ytdlp-update-available` when a newer yt-dlp is out,
+/// so users can update before YouTube changes break downloads. Asks GitHub
+/// at most once per `UPDATE_CHECK_INTERVAL`, reusing the last answer otherwise.
+pub fn check_ytdlp_update_on_startup(app: &AppHandle) {
+    if current_settings().check_ytdlp_update_on_startup == Some(false) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let cached = load_update_check(&app).filter(|check| {
+            unix_now().saturating_sub(check.checked_at) < UPDATE_CHECK_INTERVAL.as_secs()
+        });
+        let latest_version = match cached {
+            Some(check) => check.latest_version,
+            None => match latest_ytdlp_version().await {
+                Ok(latest_version) => {
+                    save_update_check(&app, &latest_version);
+                    latest_version
+                }
+                Err(err) => {
+                    tracing::warn!("Startup yt-dlp update check failed: {}", err);
+                    return;
+                }
+            },
+        };
+        let current_version = match current_ytdlp_version(&app).await {
+            Ok(current_version) => current_version,
+            Err(err) => {
+                tracing::warn!("Failed to read the yt-dlp version: {}", err);
+                return;
+            }
+        };
+
+        if !current_version.is_empty() && current_version != latest_version {
+            tracing::info!("yt-dlp {} is available (have {})", latest_version, current_version);
+            let _ = app.emit(
+                "ytdlp-update-available",
+                YtDlpVersionInfo {
+                    update_available: true,
+                    current_version,
+                    latest_version,
+                },
+            );
+        }
+    });
+}
+
+/// Streams the latest release to `temp_path`, emitting `ytdlp-update-progress`.
+/// A partial file left by a failed attempt is continued with a Range request;
+/// it is only replaced when the server ignores the range.
+async fn download_ytdlp_binary(app: &AppHandle, temp_path: &Path) -> Result<(), String> {
+    // Pinned to the release tag: `latest/download` redirects to a freshly
+    // signed URL every time, so only the tag tells whether a partial file
+    // belongs to the release being downloaded.
+    let tag = latest_ytdlp_version().await?;
+    let tag_path = temp_path.with_extension("new.tag");
+    let partial_tag = tokio::fs::read_to_string(&tag_path).await.ok();
+    if partial_tag.as_deref() != Some(tag.as_str()) {
+        let _ = tokio::fs::remove_file(temp_path).await;
+    }
+    let existing_bytes = tokio::fs::metadata(temp_path)
+        .await
+        .map(|meta| meta.len())
+        .unwrap_or(0);
+    let _ = tokio::fs::write(&tag_path, &tag).await;
 
     let client = reqwest::Client::new();
-    let response = client
-        .get("https://github.com/yt-dlp/yt-dlp/releases/latest/download/yt-dlp.exe")
+    let mut request = client.get(format!("{}/{}/yt-dlp.exe", YTDLP_RELEASES_URL, tag));
+    if existing_bytes > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", existing_bytes));
+    }
+    let mut response = request
         .send()
         .await
         .map_err(|e| format!("Failed to download yt-dlp: {}", e))?;
 
+    // The partial file already holds the whole binary, or is stale.
+    if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        let _ = tokio::fs::remove_file(temp_path).await;
+        return Box::pin(download_ytdlp_binary(app, temp_path)).await;
+    }
     if !response.status().is_success() {
         return Err(format!("Download failed with status: {}", response.status()));
     }
 
-    let bytes = response
-        .bytes()
+    let resumed = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    let mut downloaded_bytes = if resumed { existing_bytes } else { 0 };
+    let total_bytes = response
+        .content_length()
+        .map(|length| length + downloaded_bytes);
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(temp_path)
         .await
-        .map_err(|e| format!("Failed to read download: {}", e))?;
+        .map_err(|e| format!("Failed to write yt-dlp: {}", e))?;
+
+    let mut last_percent = None;
+    loop {
+        let chunk = response
+            .chunk()
+            .await
+            .map_err(|e| format!("Failed to read download: {}", e))?;
+        let Some(chunk) = chunk else {
+            break;
+        };
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write yt-dlp: {}", e))?;
+        downloaded_bytes += chunk.len() as u64;
+
+        let percent = total_bytes
+            .filter(|total| *total > 0)
+            .map(|total| downloaded_bytes as f64 / total as f64 * 100.0);
+        // One event per whole percent is plenty for a progress bar.
+        let whole_percent = percent.map(|percent| percent.floor() as u64);
+        if whole_percent.is_none() || whole_percent != last_percent {
+            last_percent = whole_percent;
+            let _ = app.emit(
+                "ytdlp-update-progress",
+                YtDlpUpdateProgress {
+                    downloaded_bytes,
+                    total_bytes,
+                    percent,
+                    resumed,
+                },
+            );
+        }
+    }
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to write yt-dlp: {}", e))?;
+
+    if total_bytes.is_some_and(|total| downloaded_bytes < total) {
+        return Err("yt-dlp download ended early; retry to resume it".to_string());
+    }
+    let _ = tokio::fs::remove_file(&tag_path).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_ytdlp(app: AppHandle) -> Result<String, String> {
+    // Replacing the bundled copy would leave the one actually in use as is.
+    if let Some(path) = custom_ytdlp_path() {
+        return Err(format!(
+            "yt-dlp runs from the custom path {:?}; update that copy (e.g. with `yt-dlp -U`) \
+             or clear the custom path to update the bundled one",
+            path
+        ));
+    }
+    let ytdlp_path = get_ytdlp_path()?;
+    tracing::debug!("Updating yt-dlp at: {:?}", ytdlp_path);
 
     let temp_path = ytdlp_path.with_extension("exe.new");
-    std::fs::write(&temp_path, &bytes).map_err(|e| format!("Failed to write yt-dlp: {}", e))?;
+    download_ytdlp_binary(&app, &temp_path).await?;
 
     let backup_path = ytdlp_path.with_extension("exe.old");
     let _ = std::fs::remove_file(&backup_path);
@@ -113,16 +299,11 @@ pub async fn update_ytdlp(app: AppHandle) -> Result<String, String> {
         .map_err(|e| format!("Failed to install new yt-dlp: {}", e))?;
 
     let _ = std::fs::remove_file(&backup_path);
+    clear_extractor_cache();
+    clear_format_cache();
 
-    let sidecar_command = app.shell().sidecar("yt-dlp").map_err(|e| e.to_string())?;
-    let output = sidecar_command
-        .args(vec!["--version"])
-        .output()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    let new_version = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    println!("[DEBUG] yt-dlp updated to version: {}", new_version);
+    let new_version = current_ytdlp_version(&app).await?;
+    tracing::debug!("yt-dlp updated to version: {}", new_version);
 
     Ok(new_version)
 }